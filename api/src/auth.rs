@@ -15,6 +15,7 @@ pub struct AuthState {
     pub email: Option<String>,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for AuthState {
     fn default() -> Self {
         Self {
@@ -1,12 +1,46 @@
 use std::env;
 
+use chrono::Weekday;
+use chrono_tz::Tz;
+
+use crate::services::SmtpSecurity;
+
 pub struct Config {
     pub qdrant_url: String,
     pub embedder_url: String,
+    pub embedder_timeout_ms: u64,
+    pub embedder_connect_timeout_ms: u64,
+    pub embedder_max_retries: u32,
+    pub embedder_retry_base_ms: u64,
+    pub embedder_breaker_threshold: u32,
+    pub embedder_breaker_cooldown_secs: u64,
+    pub embedding_model: String,
+    pub embedding_dim: u64,
+    pub embedding_passage_prefix: String,
+    pub embedding_query_prefix: String,
+    pub reindex_on_model_change: bool,
+    pub tag_case_fold: bool,
+    pub tag_max_depth: usize,
+    pub tag_max_length: usize,
+    pub duplicate_threshold: f32,
+    pub cluster_count: usize,
+    pub cluster_interval_secs: u64,
+    pub archive_on_complete: bool,
+    pub default_timezone: Tz,
+    pub week_start: Weekday,
     pub host: String,
     pub port: u16,
     pub cf_team_domain: Option<String>,
     pub cf_policy_aud: Option<String>,
+    pub calendar_token: Option<String>,
+    pub reminder_webhook_url: Option<String>,
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub reminder_email_from: Option<String>,
+    pub reminder_email_to: Option<String>,
 }
 
 impl Config {
@@ -15,13 +49,53 @@ impl Config {
             qdrant_url: env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6334".into()),
             embedder_url: env::var("EMBEDDER_URL")
                 .unwrap_or_else(|_| "http://localhost:8000".into()),
+            embedder_timeout_ms: parse_env("EMBEDDER_TIMEOUT_MS", 10_000),
+            embedder_connect_timeout_ms: parse_env("EMBEDDER_CONNECT_TIMEOUT_MS", 2_000),
+            embedder_max_retries: parse_env("EMBEDDER_MAX_RETRIES", 3),
+            embedder_retry_base_ms: parse_env("EMBEDDER_RETRY_BASE_MS", 200),
+            embedder_breaker_threshold: parse_env("EMBEDDER_BREAKER_THRESHOLD", 5),
+            embedder_breaker_cooldown_secs: parse_env("EMBEDDER_BREAKER_COOLDOWN_SECS", 30),
+            embedding_model: env::var("EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "intfloat/multilingual-e5-base".into()),
+            embedding_dim: parse_env("EMBEDDING_DIM", 768),
+            embedding_passage_prefix: env::var("EMBEDDING_PASSAGE_PREFIX")
+                .unwrap_or_else(|_| "passage: ".into()),
+            embedding_query_prefix: env::var("EMBEDDING_QUERY_PREFIX")
+                .unwrap_or_else(|_| "query: ".into()),
+            reindex_on_model_change: parse_env("REINDEX_ON_MODEL_CHANGE", false),
+            tag_case_fold: parse_env("TAG_CASE_FOLD", true),
+            tag_max_depth: parse_env("TAG_MAX_DEPTH", 5),
+            tag_max_length: parse_env("TAG_MAX_LENGTH", 100),
+            duplicate_threshold: parse_env("DUPLICATE_THRESHOLD", 0.95),
+            cluster_count: parse_env("CLUSTER_COUNT", 0),
+            cluster_interval_secs: parse_env("CLUSTER_INTERVAL_SECS", 6 * 60 * 60),
+            archive_on_complete: parse_env("ARCHIVE_ON_COMPLETE", false),
+            default_timezone: parse_env("DEFAULT_TIMEZONE", Tz::UTC),
+            week_start: parse_env("WEEK_START", Weekday::Mon),
             host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
-            port: env::var("PORT")
-                .ok()
-                .and_then(|p| p.parse().ok())
-                .unwrap_or(8080),
+            port: parse_env("PORT", 8080),
             cf_team_domain: env::var("CF_TEAM_DOMAIN").ok(),
             cf_policy_aud: env::var("CF_POLICY_AUD").ok(),
+            calendar_token: env::var("CALENDAR_TOKEN").ok().filter(|t| !t.is_empty()),
+            reminder_webhook_url: optional_env("REMINDER_WEBHOOK_URL"),
+            smtp_host: optional_env("SMTP_HOST"),
+            smtp_port: parse_env("SMTP_PORT", 587),
+            smtp_security: parse_env("SMTP_SECURITY", SmtpSecurity::StartTls),
+            smtp_username: optional_env("SMTP_USERNAME"),
+            smtp_password: optional_env("SMTP_PASSWORD"),
+            reminder_email_from: optional_env("REMINDER_EMAIL_FROM"),
+            reminder_email_to: optional_env("REMINDER_EMAIL_TO"),
         }
     }
 }
+
+fn parse_env<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+fn optional_env(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
@@ -22,6 +22,9 @@ pub enum AppError {
     #[error("Embedder error: {0}")]
     Embedder(String),
 
+    #[error("Embedder unavailable: {0}")]
+    EmbedderUnavailable(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -34,6 +37,7 @@ impl IntoResponse for AppError {
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
             AppError::Qdrant(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
             AppError::Embedder(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
+            AppError::EmbedderUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone()),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
         };
 
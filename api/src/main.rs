@@ -6,6 +6,7 @@ mod routes;
 mod services;
 
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use axum::{middleware, Router};
 use eyre::Result;
@@ -14,12 +15,28 @@ use tower_http::trace::TraceLayer;
 
 use auth::{auth_middleware, JwtValidator};
 use config::Config;
-use services::{EmbedderClient, QdrantService};
+use models::EmbeddingModel;
+use services::{
+    run_migrations, Calendar, ClusteringJob, DuplicateDetector, EmailChannel, EmbedderClient,
+    EmbedderOptions, EmbeddingQueue, MigrationContext, QdrantService, ReindexJob, ReminderChannel,
+    ReminderScheduler, SmtpOptions, TagNormalizer, TagRegistry,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub qdrant: QdrantService,
+    pub calendar: Calendar,
     pub embedder: EmbedderClient,
+    pub embedding_queue: EmbeddingQueue,
+    pub reindex: ReindexJob,
+    pub tags: TagRegistry,
+    pub duplicates: DuplicateDetector,
+    pub clusters: ClusteringJob,
+    /// 完了にしたメモをすぐ memos_archive に移すか
+    pub archive_on_complete: bool,
+    /// `GET /calendar.ics?token=` の購読用トークン。未設定ならトークンでは認証しない
+    pub calendar_token: Option<String>,
+    pub reminders: ReminderScheduler,
     pub jwt_validator: JwtValidator,
 }
 
@@ -38,13 +55,139 @@ async fn main() -> Result<()> {
 
     let config = Config::from_env();
 
+    let embedder = EmbedderClient::new(
+        config.embedder_url.clone(),
+        EmbedderOptions {
+            timeout: Duration::from_millis(config.embedder_timeout_ms),
+            connect_timeout: Duration::from_millis(config.embedder_connect_timeout_ms),
+            max_retries: config.embedder_max_retries,
+            retry_base_delay: Duration::from_millis(config.embedder_retry_base_ms),
+            breaker_threshold: config.embedder_breaker_threshold,
+            breaker_cooldown: Duration::from_secs(config.embedder_breaker_cooldown_secs),
+            passage_prefix: config.embedding_passage_prefix.clone(),
+            query_prefix: config.embedding_query_prefix.clone(),
+        },
+    )?;
+    tracing::info!("Embedder client configured for {}", config.embedder_url);
+
+    let live_model = match embedder.model_info().await {
+        Ok(model) => Some(model),
+        Err(e) => {
+            tracing::warn!(
+                "Could not read embedder model ({}), using configured model",
+                e
+            );
+            None
+        }
+    };
+    let configured_model = EmbeddingModel {
+        id: config.embedding_model.clone(),
+        dimension: config.embedding_dim,
+    };
+
     tracing::info!("Connecting to Qdrant at {}", config.qdrant_url);
-    let qdrant = QdrantService::new(&config.qdrant_url).await?;
+    let calendar = Calendar {
+        timezone: config.default_timezone,
+        week_start: config.week_start,
+    };
+    let qdrant = QdrantService::new(
+        &config.qdrant_url,
+        live_model.clone().unwrap_or(configured_model),
+        calendar,
+    )
+    .await?;
     qdrant.ensure_collections().await?;
+    let tag_normalizer = TagNormalizer {
+        case_fold: config.tag_case_fold,
+        max_depth: config.tag_max_depth,
+        max_length: config.tag_max_length,
+    };
+    let migrated = run_migrations(
+        &qdrant,
+        &MigrationContext {
+            tag_normalizer: tag_normalizer.clone(),
+            timezone: config.default_timezone,
+        },
+    )
+    .await?;
+    let tags = TagRegistry::new(qdrant.clone(), tag_normalizer);
+    tags.ensure_collections().await?;
+    if migrated {
+        // マイグレーションでタグが書き換わっている可能性がある
+        for demo in [false, true] {
+            tags.rebuild(demo).await?;
+        }
+    }
     tracing::info!("Qdrant collections ready");
 
-    let embedder = EmbedderClient::new(config.embedder_url.clone());
-    tracing::info!("Embedder client configured for {}", config.embedder_url);
+    // 既存ベクトルと同じモデルなら続けて書き込む。ズレていれば再インデックスするか、起動を止める
+    let reindex = ReindexJob::new();
+    if let Some(stored_model) = qdrant.active_model().await? {
+        match live_model.filter(|m| *m != stored_model) {
+            None => qdrant.set_current_model(stored_model),
+            Some(live_model) => {
+                tracing::warn!(
+                    "Embedder model {} ({}d) differs from indexed model {} ({}d)",
+                    live_model.id,
+                    live_model.dimension,
+                    stored_model.id,
+                    stored_model.dimension
+                );
+                if !config.reindex_on_model_change {
+                    eyre::bail!(
+                        "Embedder model {} does not match indexed model {}. \
+                         Set REINDEX_ON_MODEL_CHANGE=true to re-embed existing memos, \
+                         or point EMBEDDER_URL at the indexed model",
+                        live_model.id,
+                        stored_model.id
+                    );
+                }
+                // 張り替えまではエイリアスの先が stored_model のコレクションなので、
+                // ベクトル化を止めて保留・語句検索に回す
+                qdrant.set_current_model(stored_model);
+                embedder.set_switching_model(true);
+                reindex.start(qdrant.clone(), embedder.clone())?;
+            }
+        }
+    }
+
+    let duplicates = DuplicateDetector::new(qdrant.clone(), config.duplicate_threshold);
+    let clusters = ClusteringJob::start(
+        qdrant.clone(),
+        config.cluster_count,
+        Duration::from_secs(config.cluster_interval_secs),
+    );
+    let embedding_queue = EmbeddingQueue::start(qdrant.clone(), embedder.clone());
+
+    let mut reminder_channels = Vec::new();
+    if let Some(url) = config.reminder_webhook_url.clone() {
+        reminder_channels.push(ReminderChannel::webhook(url)?);
+    }
+    match (
+        config.smtp_host.clone(),
+        config.reminder_email_from.clone(),
+        config.reminder_email_to.clone(),
+    ) {
+        (Some(host), Some(from), Some(to)) => {
+            let email = EmailChannel::new(SmtpOptions {
+                host,
+                port: config.smtp_port,
+                security: config.smtp_security,
+                username: config.smtp_username.clone(),
+                password: config.smtp_password.clone(),
+                from,
+                to,
+            })?;
+            reminder_channels.push(ReminderChannel::Email(Box::new(email)));
+        }
+        (Some(_), _, _) => {
+            tracing::warn!("Reminder email disabled (missing REMINDER_EMAIL_FROM or REMINDER_EMAIL_TO)");
+        }
+        _ => {}
+    }
+    let reminders = ReminderScheduler::new(qdrant.clone(), reminder_channels);
+    reminders.ensure_collection().await?;
+    reminders.start();
 
     let jwt_validator = if config.cf_team_domain.is_some() && config.cf_policy_aud.is_some() {
         let validator = JwtValidator::new(
@@ -60,7 +203,16 @@ async fn main() -> Result<()> {
 
     let state = AppState {
         qdrant,
+        calendar,
         embedder,
+        embedding_queue,
+        reindex,
+        tags,
+        duplicates,
+        clusters,
+        archive_on_complete: config.archive_on_complete,
+        calendar_token: config.calendar_token.clone(),
+        reminders,
         jwt_validator,
     };
 
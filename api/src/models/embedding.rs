@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// ベクトルを生成したモデル。payload とコレクションのメタデータに記録する
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmbeddingModel {
+    #[serde(rename = "model")]
+    pub id: String,
+    pub dimension: u64,
+}
@@ -1,7 +1,10 @@
 use chrono::{DateTime, NaiveDate, Utc};
-use serde::{Deserialize, Serialize};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
+use super::{MemoDate, Recurrence};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum MemoType {
@@ -9,19 +12,36 @@ pub enum MemoType {
     Permanent,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingStatus {
+    #[default]
+    Ready,
+    Pending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Memo {
     pub id: Uuid,
     pub content: String,
     #[serde(rename = "type")]
     pub memo_type: MemoType,
-    pub from: Option<NaiveDate>,
-    pub until: Option<NaiveDate>,
+    pub from: Option<MemoDate>,
+    pub until: Option<MemoDate>,
+    /// from / until の時刻と日付の境界のタイムゾーン。None なら `DEFAULT_TIMEZONE`
+    pub timezone: Option<Tz>,
     pub tags: Vec<String>,
     pub date_added: DateTime<Utc>,
     pub access_count: u32,
     pub last_accessed: DateTime<Utc>,
     pub completed: bool,
+    pub embedding_status: EmbeddingStatus,
+    /// iCalendar から取り込んだイベントの UID。再取り込み時の重複判定に使う
+    pub ics_uid: Option<String>,
+    /// 繰り返しルール。from が初回で、各回は from〜until と同じ日数になる
+    pub recurrence: Option<Recurrence>,
+    /// 繰り返しメモで完了にした回 (各回の初日)。系列全体の完了は `completed`
+    pub completed_occurrences: Vec<NaiveDate>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,10 +49,18 @@ pub struct CreateMemoRequest {
     pub content: String,
     #[serde(rename = "type")]
     pub memo_type: MemoType,
-    pub from: Option<NaiveDate>,
-    pub until: Option<NaiveDate>,
+    /// `2026-01-20` (終日) または `2026-01-20T15:00`
+    pub from: Option<MemoDate>,
+    pub until: Option<MemoDate>,
+    /// IANA 名 (`Asia/Tokyo`)。省略時は `DEFAULT_TIMEZONE`
+    pub timezone: Option<Tz>,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// RRULE (`FREQ=WEEKLY;BYDAY=TU` など)。from が必要
+    pub recurrence: Option<Recurrence>,
+    /// true なら重複候補があっても作成する
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,22 +68,56 @@ pub struct UpdateMemoRequest {
     pub content: Option<String>,
     #[serde(rename = "type")]
     pub memo_type: Option<MemoType>,
-    pub from: Option<NaiveDate>,
-    pub until: Option<NaiveDate>,
+    pub from: Option<MemoDate>,
+    pub until: Option<MemoDate>,
+    /// `null` で外す (`DEFAULT_TIMEZONE` に戻る)
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub timezone: Option<Option<Tz>>,
     pub tags: Option<Vec<String>>,
     pub completed: Option<bool>,
+    /// `null` で繰り返しを解除する
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub recurrence: Option<Option<Recurrence>>,
 }
 
-#[derive(Debug, Serialize)]
+/// 省略 (None) と `null` (Some(None)) を区別する
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeMemosRequest {
+    pub ids: Vec<Uuid>,
+    #[serde(default = "default_merge_separator")]
+    pub separator: String,
+    /// true なら元のメモを削除せず memos_archive に移す
+    #[serde(default)]
+    pub archive_sources: bool,
+}
+
+fn default_merge_separator() -> String {
+    "\n\n".into()
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct MemoResponse {
     pub id: Uuid,
     pub content: String,
     #[serde(rename = "type")]
     pub memo_type: MemoType,
-    pub from: Option<NaiveDate>,
-    pub until: Option<NaiveDate>,
+    pub from: Option<MemoDate>,
+    pub until: Option<MemoDate>,
+    pub timezone: Option<Tz>,
     pub tags: Vec<String>,
     pub date_added: DateTime<Utc>,
+    pub completed: bool,
+    pub embedding_status: EmbeddingStatus,
+    pub recurrence: Option<Recurrence>,
+    pub completed_occurrences: Vec<NaiveDate>,
 }
 
 impl From<Memo> for MemoResponse {
@@ -66,8 +128,13 @@ impl From<Memo> for MemoResponse {
             memo_type: memo.memo_type,
             from: memo.from,
             until: memo.until,
+            timezone: memo.timezone,
             tags: memo.tags,
             date_added: memo.date_added,
+            completed: memo.completed,
+            embedding_status: memo.embedding_status,
+            recurrence: memo.recurrence,
+            completed_occurrences: memo.completed_occurrences,
         }
     }
 }
@@ -79,20 +146,118 @@ pub struct SearchRequest {
     pub filters: SearchFilters,
     #[serde(default = "default_limit")]
     pub limit: u32,
+    /// 0-1。指定すると似通った結果を減らすよう MMR で並べ替える (0 は関連度のみ)
+    pub diversity: Option<f32>,
 }
 
 fn default_limit() -> u32 {
     20
 }
 
+/// 相対的な日付フィルタ。`SearchFilters::timezone` での今日を基準に解決する
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePreset {
+    Today,
+    Tomorrow,
+    ThisWeek,
+    NextWeek,
+    ThisMonth,
+}
+
+/// 日付区間の一致条件。期間を持つメモ (from〜until) の扱いが変わる
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateMatch {
+    /// 区間と1日でも重なる
+    #[default]
+    Overlaps,
+    /// 期間全体が区間に収まる
+    Contained,
+    /// 初日が区間内
+    StartsWithin,
+    /// 1日だけのメモで、その日が区間内
+    SingleDay,
+}
+
 #[derive(Debug, Default, Deserialize)]
 pub struct SearchFilters {
     pub from_gte: Option<NaiveDate>,
     pub until_lte: Option<NaiveDate>,
+    /// from_gte / until_lte を明示した場合はそちらを優先する
+    pub preset: Option<DatePreset>,
+    /// 日付の境界とプリセットの基準にするIANAタイムゾーン。省略時はサーバーの既定
+    pub timezone: Option<Tz>,
     #[serde(default)]
+    pub date_match: DateMatch,
+    #[serde(default, deserialize_with = "one_or_many")]
     pub tags: Vec<String>,
     #[serde(rename = "type")]
     pub memo_type: Option<MemoType>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub completed: Option<bool>,
+    /// 本文にそのまま含まれている必要がある語句
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub phrases: Vec<String>,
+}
+
+/// クエリ文字列から `#[serde(flatten)]` で読むと、値が1つだけの配列は文字列のまま届く
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// クエリ文字列から `#[serde(flatten)]` で読むと、真偽値も `"true"` のような文字列で届く
+fn bool_or_string<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    match Option::<BoolOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(BoolOrString::Bool(value)) => Ok(Some(value)),
+        Some(BoolOrString::String(value)) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("{} is not a boolean", value))),
+    }
+}
+
+/// 検索クエリから読み取った演算子。UIでチップとして表示する
+#[derive(Debug, Default, Serialize)]
+pub struct QueryInterpretation {
+    /// 演算子を除いた自由文 (ベクトル化する部分)
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub phrases: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub memo_type: Option<MemoType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_gte: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until_lte: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -101,12 +266,35 @@ pub struct SearchResult {
     pub content: String,
     pub score: f32,
     pub tags: Vec<String>,
-    pub from: Option<NaiveDate>,
+    pub from: Option<MemoDate>,
     pub date_added: DateTime<Utc>,
+    pub completed: bool,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
     pub total: usize,
+    /// Embedder停止中で語彙一致にフォールバックした場合は true
+    pub degraded: bool,
+    /// クエリの解釈。関連メモなどクエリの無い検索では省略する
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpretation: Option<QueryInterpretation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_distinguishes_missing_and_null_timezone() {
+        let missing: UpdateMemoRequest = serde_json::from_str("{}").unwrap();
+        assert_eq!(missing.timezone, None);
+
+        let cleared: UpdateMemoRequest = serde_json::from_str(r#"{"timezone": null}"#).unwrap();
+        assert_eq!(cleared.timezone, Some(None));
+
+        let set: UpdateMemoRequest = serde_json::from_str(r#"{"timezone": "Asia/Tokyo"}"#).unwrap();
+        assert_eq!(set.timezone, Some(Some(chrono_tz::Asia::Tokyo)));
+    }
 }
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// メモの日付。時刻は省略でき、省略すると終日 (その日全体) になる。
+/// 時刻はメモの `timezone` (無ければ `DEFAULT_TIMEZONE`) の壁時計の時刻。
+/// API と payload では `2026-01-20` / `2026-01-20T15:00` の文字列でやりとりする
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MemoDate {
+    pub date: NaiveDate,
+    /// 同じ日なら終日 (None) が時刻付きより前に並ぶ
+    pub time: Option<NaiveTime>,
+}
+
+impl MemoDate {
+    /// メモに付けられる年の範囲。書き出しや繰り返しの展開で日付の計算があふれないよう狭めておく
+    pub const MIN_YEAR: i32 = 1900;
+    pub const MAX_YEAR: i32 = 2199;
+
+    pub fn is_supported(&self) -> bool {
+        (Self::MIN_YEAR..=Self::MAX_YEAR).contains(&self.date.year())
+    }
+
+    /// 時刻はそのままで日付だけを変える (繰り返しメモの各回)
+    pub fn with_date(self, date: NaiveDate) -> Self {
+        Self { date, ..self }
+    }
+}
+
+impl From<NaiveDate> for MemoDate {
+    fn from(date: NaiveDate) -> Self {
+        Self { date, time: None }
+    }
+}
+
+impl From<NaiveDateTime> for MemoDate {
+    fn from(datetime: NaiveDateTime) -> Self {
+        Self {
+            date: datetime.date(),
+            time: Some(datetime.time()),
+        }
+    }
+}
+
+impl FromStr for MemoDate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(date.into());
+        }
+        [
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%d %H:%M:%S",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .map(Into::into)
+        .ok_or_else(|| format!("{} must be YYYY-MM-DD or YYYY-MM-DDTHH:MM", s))
+    }
+}
+
+impl fmt::Display for MemoDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+        match self.time {
+            Some(time) if time.second() != 0 => write!(f, "T{}", time.format("%H:%M:%S")),
+            Some(time) => write!(f, "T{}", time.format("%H:%M")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TryFrom<String> for MemoDate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<MemoDate> for String {
+    fn from(date: MemoDate) -> Self {
+        date.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn date_only_round_trips() {
+        let parsed: MemoDate = "2026-01-20".parse().unwrap();
+        assert_eq!(parsed, date(2026, 1, 20).into());
+        assert_eq!(parsed.time, None);
+        assert_eq!(parsed.to_string(), "2026-01-20");
+    }
+
+    #[test]
+    fn time_round_trips() {
+        let parsed: MemoDate = "2026-01-20T15:00".parse().unwrap();
+        assert_eq!(parsed.date, date(2026, 1, 20));
+        assert_eq!(parsed.time, NaiveTime::from_hms_opt(15, 0, 0));
+        assert_eq!(parsed.to_string(), "2026-01-20T15:00");
+
+        let spaced: MemoDate = " 2026-01-20 15:00 ".parse().unwrap();
+        assert_eq!(spaced, parsed);
+    }
+
+    #[test]
+    fn seconds_are_kept_only_when_set() {
+        let parsed: MemoDate = "2026-01-20T15:00:30".parse().unwrap();
+        assert_eq!(parsed.time, NaiveTime::from_hms_opt(15, 0, 30));
+        assert_eq!(parsed.to_string(), "2026-01-20T15:00:30");
+
+        let zero: MemoDate = "2026-01-20T15:00:00".parse().unwrap();
+        assert_eq!(zero.to_string(), "2026-01-20T15:00");
+    }
+
+    #[test]
+    fn display_and_serde_use_the_same_form() {
+        let memo_date = MemoDate::from(date(2026, 3, 5).and_hms_opt(9, 5, 0).unwrap());
+        assert_eq!(memo_date.to_string(), "2026-03-05T09:05");
+        assert_eq!(
+            serde_json::to_string(&memo_date).unwrap(),
+            "\"2026-03-05T09:05\""
+        );
+        let parsed: MemoDate = serde_json::from_str("\"2026-03-05T09:05\"").unwrap();
+        assert_eq!(parsed, memo_date);
+    }
+
+    #[test]
+    fn rejects_other_forms() {
+        assert!("2026-01-20T15:00+09:00".parse::<MemoDate>().is_err());
+        assert!("2026/01/20".parse::<MemoDate>().is_err());
+        assert!("2026-02-30".parse::<MemoDate>().is_err());
+        assert!(serde_json::from_str::<MemoDate>("\"tomorrow\"").is_err());
+    }
+
+    #[test]
+    fn supported_years() {
+        assert!(MemoDate::from(date(1900, 1, 1)).is_supported());
+        assert!(MemoDate::from(date(2199, 12, 31)).is_supported());
+        assert!(!MemoDate::from(date(1899, 12, 31)).is_supported());
+        // 書式としては読めるが、範囲外
+        let far: MemoDate = "+262142-12-31".parse().unwrap();
+        assert!(!far.is_supported());
+    }
+
+    #[test]
+    fn all_day_sorts_before_timed() {
+        let all_day = MemoDate::from(date(2026, 1, 20));
+        let timed: MemoDate = "2026-01-20T00:00".parse().unwrap();
+        assert!(all_day < timed);
+        assert_eq!(
+            timed.with_date(date(2026, 1, 27)).to_string(),
+            "2026-01-27T00:00"
+        );
+    }
+}
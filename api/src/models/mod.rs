@@ -1,3 +1,11 @@
+mod embedding;
 mod memo;
+mod memo_date;
+mod recurrence;
+mod reminder;
 
+pub use embedding::*;
 pub use memo::*;
+pub use memo_date::*;
+pub use recurrence::*;
+pub use reminder::*;
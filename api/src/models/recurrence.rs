@@ -0,0 +1,345 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// COUNT の上限。系列の展開 (最終回の計算) を保存のたびに行うので大きすぎる値は断る
+const MAX_COUNT: u32 = 10_000;
+/// UNTIL の年の上限 (理由は MAX_COUNT と同じ)
+const MAX_UNTIL_YEAR: i32 = 2199;
+
+/// 繰り返しの単位 (RRULE の FREQ)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// BYDAY の1項目。`ordinal` は MONTHLY での「第2火曜」(2) や「最終金曜」(-1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekdayRule {
+    pub ordinal: Option<i8>,
+    pub weekday: Weekday,
+}
+
+/// 繰り返しルール。RFC 5545 の RRULE のうち日付単位で使う部分だけを扱う。
+/// API と payload では `FREQ=WEEKLY;BYDAY=TU` の文字列でやりとりする
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<WeekdayRule>,
+    /// 負の値は月末から数える (-1 は月末日)
+    pub by_month_day: Vec<i8>,
+    pub count: Option<u32>,
+    /// この日を含む
+    pub until: Option<NaiveDate>,
+    /// WEEKLY で INTERVAL が2以上のときの週の区切り
+    pub week_start: Weekday,
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut frequency = None;
+        let mut rule = Recurrence {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            count: None,
+            until: None,
+            week_start: Weekday::Mon,
+        };
+        let mut seen = Vec::new();
+
+        for part in s.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("{}: expected KEY=VALUE", part))?;
+            let key = key.to_uppercase();
+            if seen.contains(&key) {
+                return Err(format!("{} is given more than once", key));
+            }
+            seen.push(key.clone());
+
+            match key.as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("FREQ={} is not supported", other)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval =
+                        value.parse().ok().filter(|n| *n >= 1).ok_or_else(|| {
+                            format!("INTERVAL={} must be a positive integer", value)
+                        })?
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|n| (1..=MAX_COUNT).contains(n))
+                            .ok_or_else(|| {
+                                format!("COUNT={} must be between 1 and {}", value, MAX_COUNT)
+                            })?,
+                    )
+                }
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(parse_weekday_rule)
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|day| {
+                            day.parse::<i8>()
+                                .ok()
+                                .filter(|d| *d != 0 && d.abs() <= 31)
+                                .ok_or_else(|| format!("BYMONTHDAY={} is out of range", day))
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "WKST" => rule.week_start = parse_weekday(value)?,
+                other => return Err(format!("{} is not supported", other)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or("FREQ is required")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("COUNT and UNTIL cannot be combined".into());
+        }
+        if !rule.by_month_day.is_empty() && rule.frequency != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported with FREQ=MONTHLY".into());
+        }
+        if !rule.by_month_day.is_empty() && !rule.by_day.is_empty() {
+            return Err("BYDAY and BYMONTHDAY cannot be combined".into());
+        }
+        if !rule.by_day.is_empty() && rule.frequency == Frequency::Yearly {
+            return Err("BYDAY is not supported with FREQ=YEARLY".into());
+        }
+        if rule.frequency != Frequency::Monthly && rule.by_day.iter().any(|d| d.ordinal.is_some()) {
+            return Err("Numbered BYDAY (e.g. 2TU) is only supported with FREQ=MONTHLY".into());
+        }
+
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|d| match d.ordinal {
+                    Some(n) => format!("{}{}", n, weekday_code(d.weekday)),
+                    None => weekday_code(d.weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(i8::to_string).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if self.week_start != Weekday::Mon {
+            write!(f, ";WKST={}", weekday_code(self.week_start))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(rule: Recurrence) -> Self {
+        rule.to_string()
+    }
+}
+
+/// `20261231` または `20261231T235959Z`。時刻は使わず日付だけを見る
+fn parse_until(value: &str) -> Result<NaiveDate, String> {
+    let (date, time) = value.split_at(value.len().min(8));
+    if !time.is_empty() && !time.starts_with('T') {
+        return Err(format!("UNTIL={} must be a date (YYYYMMDD)", value));
+    }
+    let until = NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map_err(|_| format!("UNTIL={} must be a date (YYYYMMDD)", value))?;
+    if until.year() > MAX_UNTIL_YEAR {
+        return Err(format!(
+            "UNTIL={} must not be later than {}",
+            value, MAX_UNTIL_YEAR
+        ));
+    }
+    Ok(until)
+}
+
+/// `TU`、`2TU`、`-1FR`
+fn parse_weekday_rule(value: &str) -> Result<WeekdayRule, String> {
+    let split = value.len().saturating_sub(2);
+    let (ordinal, day) = value.split_at(split);
+    let weekday = parse_weekday(day)?;
+    let ordinal = match ordinal {
+        "" => None,
+        n => Some(
+            n.parse::<i8>()
+                .ok()
+                .filter(|n| *n != 0 && n.abs() <= 5)
+                .ok_or_else(|| format!("BYDAY={} is out of range", value))?,
+        ),
+    };
+    Ok(WeekdayRule { ordinal, weekday })
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    match value.to_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("{} is not a weekday (MO-SU)", value)),
+    }
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rule: &str) -> Recurrence {
+        rule.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_ordinal_and_negative_byday() {
+        let rule = parse("FREQ=MONTHLY;BYDAY=2TU,-1FR");
+        assert_eq!(rule.frequency, Frequency::Monthly);
+        assert_eq!(
+            rule.by_day,
+            vec![
+                WeekdayRule {
+                    ordinal: Some(2),
+                    weekday: Weekday::Tue
+                },
+                WeekdayRule {
+                    ordinal: Some(-1),
+                    weekday: Weekday::Fri
+                },
+            ]
+        );
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;BYDAY=2TU,-1FR");
+    }
+
+    #[test]
+    fn parses_negative_bymonthday() {
+        let rule = parse("FREQ=MONTHLY;BYMONTHDAY=1,-1");
+        assert_eq!(rule.by_month_day, vec![1, -1]);
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;BYMONTHDAY=1,-1");
+    }
+
+    #[test]
+    fn round_trips_interval_and_wkst() {
+        let rule = parse("RRULE:freq=weekly;INTERVAL=2;BYDAY=TU,SU;WKST=SU;COUNT=4");
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.week_start, Weekday::Sun);
+        assert_eq!(rule.count, Some(4));
+        assert_eq!(
+            rule.to_string(),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,SU;WKST=SU;COUNT=4"
+        );
+    }
+
+    #[test]
+    fn until_ignores_the_time_part() {
+        let rule = parse("FREQ=DAILY;UNTIL=20261231T235959Z");
+        assert_eq!(rule.until, NaiveDate::from_ymd_opt(2026, 12, 31));
+        assert_eq!(rule.to_string(), "FREQ=DAILY;UNTIL=20261231");
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;FREQ=WEEKLY",
+            "FREQ=DAILY;COUNT=3;UNTIL=20261231",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=WEEKLY;BYDAY=2TU",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=MONTHLY;BYDAY=6TU",
+            "FREQ=MONTHLY;BYDAY=TU;BYMONTHDAY=1",
+            "FREQ=YEARLY;BYDAY=MO",
+            "FREQ=DAILY;BYHOUR=9",
+        ] {
+            assert!(
+                rule.parse::<Recurrence>().is_err(),
+                "{} should be rejected",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_count_and_until() {
+        assert!("FREQ=DAILY;COUNT=10000".parse::<Recurrence>().is_ok());
+        assert!("FREQ=DAILY;COUNT=10001".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;COUNT=4294967295".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;UNTIL=21991231".parse::<Recurrence>().is_ok());
+        assert!("FREQ=DAILY;UNTIL=99991231".parse::<Recurrence>().is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::MemoDate;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderStatus {
+    /// 通知待ち (送信に失敗して再試行待ちのものを含む)
+    Pending,
+    Sent,
+    /// 再試行の上限まで全チャネルで失敗した
+    Failed,
+    /// 通知時刻にメモが削除・完了済みだったので送らなかった
+    Cancelled,
+}
+
+impl ReminderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReminderStatus::Pending => "pending",
+            ReminderStatus::Sent => "sent",
+            ReminderStatus::Failed => "failed",
+            ReminderStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// メモに付けるリマインダー。`reminders` コレクションに保存する
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub id: Uuid,
+    pub memo_id: Uuid,
+    pub remind_at: DateTime<Utc>,
+    pub status: ReminderStatus,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    /// 送信に失敗した回数
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReminderRequest {
+    /// RFC 3339 (`2026-01-15T09:00:00+09:00`)
+    pub remind_at: DateTime<Utc>,
+}
+
+/// 各チャネルに送る通知の中身
+#[derive(Debug, Clone, Serialize)]
+pub struct ReminderNotification {
+    pub reminder_id: Uuid,
+    pub memo_id: Uuid,
+    pub remind_at: DateTime<Utc>,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub from: Option<MemoDate>,
+    pub until: Option<MemoDate>,
+    /// from / until の時刻のタイムゾーン。None なら `DEFAULT_TIMEZONE`
+    pub timezone: Option<Tz>,
+}
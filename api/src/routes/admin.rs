@@ -0,0 +1,33 @@
+use axum::{extract::State, routing::get, Json, Router};
+
+use crate::auth::AuthState;
+use crate::error::Result;
+use crate::services::ReindexStatus;
+use crate::AppState;
+
+use super::require_auth;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/admin/reindex", get(reindex_status).post(start_reindex))
+}
+
+async fn start_reindex(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Json<ReindexStatus>> {
+    require_auth(&auth)?;
+
+    let status = state
+        .reindex
+        .start(state.qdrant.clone(), state.embedder.clone())?;
+    Ok(Json(status))
+}
+
+async fn reindex_status(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Json<ReindexStatus>> {
+    require_auth(&auth)?;
+
+    Ok(Json(state.reindex.status()))
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use axum::{extract::State, routing::get, Json, Router};
+use axum_extra::extract::Query;
+use chrono::{DateTime, Days, NaiveDate};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthState;
+use crate::error::{AppError, Result};
+use crate::models::{DateMatch, Memo, MemoDate, MemoResponse, MemoType, SearchFilters};
+use crate::services::{occurrence_span, start_timestamp};
+use crate::AppState;
+
+/// 1回で取得できる日数の上限
+const MAX_AGENDA_DAYS: u64 = 366;
+/// `to` を省略したときの日数
+const DEFAULT_AGENDA_DAYS: u64 = 7;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/agenda", get(agenda))
+        .route("/demo/agenda", get(demo_agenda))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgendaQuery {
+    /// 省略時は今日
+    pub from: Option<NaiveDate>,
+    /// 省略時は from から1週間
+    pub to: Option<NaiveDate>,
+    /// 日付の境界と「今日」の基準。省略時は `DEFAULT_TIMEZONE`
+    pub timezone: Option<Tz>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(rename = "type")]
+    pub memo_type: Option<MemoType>,
+}
+
+/// アジェンダの1件。繰り返しメモは回ごとに、from / until / completed をその回のものにして返す
+#[derive(Debug, Serialize)]
+pub struct AgendaMemo {
+    #[serde(flatten)]
+    pub memo: MemoResponse,
+    /// 繰り返しメモの回 (その回の初日)。完了にするときに `occurrence` として渡す
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occurrence: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgendaDay {
+    pub date: NaiveDate,
+    pub memos: Vec<AgendaMemo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgendaResponse {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// 期限 (until、無ければ from) を過ぎた未完了のフラッシュメモ。古い順。繰り返しメモは含めない
+    pub overdue: Vec<MemoResponse>,
+    /// メモのある日だけ、日付順
+    pub days: Vec<AgendaDay>,
+}
+
+async fn agenda(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Query(query): Query<AgendaQuery>,
+) -> Result<Json<AgendaResponse>> {
+    let is_demo = !auth.is_authenticated;
+    run_agenda(&state, query, is_demo).await.map(Json)
+}
+
+async fn demo_agenda(
+    State(state): State<AppState>,
+    Query(query): Query<AgendaQuery>,
+) -> Result<Json<AgendaResponse>> {
+    run_agenda(&state, query, true).await.map(Json)
+}
+
+async fn run_agenda(state: &AppState, query: AgendaQuery, demo: bool) -> Result<AgendaResponse> {
+    let timezone = query.timezone.unwrap_or(state.calendar.timezone);
+    let today = state.calendar.today(timezone);
+    let from = query.from.unwrap_or(today);
+    let to = match query.to {
+        Some(to) => to,
+        None => from
+            .checked_add_days(Days::new(DEFAULT_AGENDA_DAYS - 1))
+            .ok_or_else(|| AppError::BadRequest("from is out of range".into()))?,
+    };
+    if to < from {
+        return Err(AppError::BadRequest("to must not be before from".into()));
+    }
+    if (to - from).num_days() as u64 >= MAX_AGENDA_DAYS {
+        return Err(AppError::BadRequest(format!(
+            "The agenda can span at most {} days",
+            MAX_AGENDA_DAYS
+        )));
+    }
+
+    let filters = SearchFilters {
+        from_gte: Some(from),
+        until_lte: Some(to),
+        timezone: Some(timezone),
+        date_match: DateMatch::Overlaps,
+        tags: state.tags.normalize(&query.tags)?,
+        memo_type: query.memo_type.clone(),
+        ..Default::default()
+    };
+    let memos = state.qdrant.matching_memos(&filters, demo).await?;
+
+    // 最終日が昨日以前の未完了フラッシュメモ
+    let overdue_filters = SearchFilters {
+        until_lte: Some(today - Days::new(1)),
+        timezone: Some(timezone),
+        date_match: DateMatch::Contained,
+        tags: filters.tags.clone(),
+        memo_type: Some(MemoType::Flash),
+        completed: Some(false),
+        ..Default::default()
+    };
+    let overdue = if query.memo_type == Some(MemoType::Permanent) {
+        Vec::new()
+    } else {
+        let mut overdue = state.qdrant.matching_memos(&overdue_filters, demo).await?;
+        overdue.retain(|m| m.recurrence.is_none());
+        overdue.sort_by_key(|m| (last_day(m), m.date_added));
+        overdue.into_iter().map(Into::into).collect()
+    };
+
+    Ok(AgendaResponse {
+        from,
+        to,
+        overdue,
+        days: group_by_day(memos, from, to, timezone, state.calendar.timezone)?,
+    })
+}
+
+/// 複数日のメモはかかっている日すべてに入れる。各日の中は未完了を先に、初日・開始時刻 (終日が先)・作成日時の順。
+/// 時刻付きのメモは `timezone` での日時に直してから日を決める (メモのタイムゾーンが無ければ `default_timezone`)
+fn group_by_day(
+    memos: Vec<Memo>,
+    from: NaiveDate,
+    to: NaiveDate,
+    timezone: Tz,
+    default_timezone: Tz,
+) -> Result<Vec<AgendaDay>> {
+    let span = |memo: &Memo| {
+        let memo_timezone = memo.timezone.unwrap_or(default_timezone);
+        let local = |date: MemoDate| in_timezone(date, memo_timezone, timezone);
+        Some((local(first_day(memo)?), local(last_day(memo)?)))
+    };
+
+    let mut entries: Vec<(Memo, Option<NaiveDate>)> = Vec::new();
+    for memo in memos {
+        match memo.recurrence {
+            Some(_) => entries.extend(expand_occurrences(memo, from, to)?),
+            None => entries.push((memo, None)),
+        }
+    }
+    entries.sort_by_cached_key(|(m, _)| {
+        let first = span(m).map(|(first, _)| first);
+        (m.completed, first, m.date_added)
+    });
+
+    let mut days: BTreeMap<NaiveDate, Vec<AgendaMemo>> = BTreeMap::new();
+    for (memo, occurrence) in entries {
+        let Some((first, last)) = span(&memo) else {
+            continue;
+        };
+        let start = first.date.max(from);
+        let end = last.date.max(first.date).min(to);
+        for date in start.iter_days().take_while(|d| *d <= end) {
+            days.entry(date).or_default().push(AgendaMemo {
+                memo: memo.clone().into(),
+                occurrence,
+            });
+        }
+    }
+
+    Ok(days
+        .into_iter()
+        .map(|(date, memos)| AgendaDay { date, memos })
+        .collect())
+}
+
+/// 繰り返しメモを `from`〜`to` にかかる回ごとのメモに展開する。
+/// タイムゾーンの違いで日がずれる回も拾えるよう前後1日ずつ広く取る (範囲外の回は `group_by_day` で落ちる)
+fn expand_occurrences(
+    memo: Memo,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(Memo, Option<NaiveDate>)>> {
+    let (Some(recurrence), Some(first)) = (&memo.recurrence, memo.from) else {
+        return Ok(Vec::new());
+    };
+    let span = Days::new(occurrence_span(first.date, memo.until.map(|d| d.date)));
+    let margin = Days::new(1);
+    let out_of_range =
+        || AppError::BadRequest(format!("Dates of memo {} are out of range", memo.id));
+    let last = to
+        .checked_add_days(margin)
+        .ok_or_else(|| AppError::BadRequest("to is out of range".into()))?;
+
+    let mut occurrences = Vec::new();
+    for date in recurrence
+        .occurrences(first.date)
+        .take_while(|date| *date <= last)
+    {
+        let end = date.checked_add_days(span).ok_or_else(out_of_range)?;
+        // あふれるほど先の回は from より後
+        if end.checked_add_days(margin).is_some_and(|end| end < from) {
+            continue;
+        }
+        let mut occurrence = memo.clone();
+        occurrence.from = Some(first.with_date(date));
+        occurrence.until = memo.until.map(|until| until.with_date(end));
+        occurrence.completed = memo.completed || memo.completed_occurrences.contains(&date);
+        occurrences.push((occurrence, Some(date)));
+    }
+    Ok(occurrences)
+}
+
+/// 時刻付きの日付を `from` から `to` のタイムゾーンの日時に直す。終日の日付はそのまま
+fn in_timezone(date: MemoDate, from: Tz, to: Tz) -> MemoDate {
+    if date.time.is_none() || from == to {
+        return date;
+    }
+    DateTime::from_timestamp(start_timestamp(date, from), 0)
+        .map(|t| t.with_timezone(&to).naive_local().into())
+        .unwrap_or(date)
+}
+
+/// 初日。from が無ければ until
+fn first_day(memo: &Memo) -> Option<MemoDate> {
+    memo.from.or(memo.until)
+}
+
+/// 最終日。until が無ければ from
+fn last_day(memo: &Memo) -> Option<MemoDate> {
+    memo.until.or(memo.from)
+}
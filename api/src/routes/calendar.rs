@@ -0,0 +1,82 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use axum_extra::extract::Query;
+use serde::Deserialize;
+
+use crate::auth::AuthState;
+use crate::error::{AppError, Result};
+use crate::models::SearchFilters;
+use crate::services::render_calendar;
+use crate::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/calendar.ics", get(calendar_feed))
+        .route("/demo/calendar.ics", get(demo_calendar_feed))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// カレンダーアプリはヘッダーを付けられないので、購読URLに `CALENDAR_TOKEN` を入れて認証する
+    pub token: Option<String>,
+    /// 指定したタグ (配下を含まない) のメモだけにする。複数指定はAND
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+async fn calendar_feed(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Response> {
+    let token_valid = match (&state.calendar_token, &query.token) {
+        (Some(expected), Some(token)) => constant_time_eq(expected.as_bytes(), token.as_bytes()),
+        _ => false,
+    };
+    if !auth.is_authenticated && !token_valid {
+        return Err(AppError::Unauthorized(
+            "Authentication or a valid calendar token is required".into(),
+        ));
+    }
+
+    run_calendar_feed(&state, query, false).await
+}
+
+async fn demo_calendar_feed(
+    State(state): State<AppState>,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Response> {
+    run_calendar_feed(&state, query, true).await
+}
+
+async fn run_calendar_feed(state: &AppState, query: CalendarQuery, demo: bool) -> Result<Response> {
+    let filters = SearchFilters {
+        tags: state.tags.normalize(&query.tags)?,
+        ..Default::default()
+    };
+    let mut memos = state.qdrant.matching_memos(&filters, demo).await?;
+    memos.retain(|m| m.from.is_some() || m.until.is_some());
+    memos.sort_by_key(|m| (m.from.or(m.until), m.date_added));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"ultnote.ics\"",
+            ),
+        ],
+        render_calendar(&memos, state.calendar.timezone),
+    )
+        .into_response())
+}
+
+/// トークン比較で一致した長さが応答時間から漏れないようにする
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,43 @@
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+
+use crate::auth::AuthState;
+use crate::error::Result;
+use crate::services::ClusterReport;
+use crate::AppState;
+
+use super::require_auth;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/clusters", get(list_clusters))
+        .route("/clusters/rebuild", post(rebuild_clusters))
+        .route("/demo/clusters", get(list_demo_clusters))
+}
+
+async fn list_clusters(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Json<ClusterReport>> {
+    let is_demo = !auth.is_authenticated;
+    let report = state.clusters.report(is_demo).await?;
+    Ok(Json(report))
+}
+
+async fn list_demo_clusters(State(state): State<AppState>) -> Result<Json<ClusterReport>> {
+    let report = state.clusters.report(true).await?;
+    Ok(Json(report))
+}
+
+async fn rebuild_clusters(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Json<ClusterReport>> {
+    require_auth(&auth)?;
+
+    let report = state.clusters.run(false).await?;
+    Ok(Json(report))
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthState;
+use crate::error::{AppError, Result};
+use crate::services::DuplicateCluster;
+use crate::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/duplicates", get(list_duplicates))
+        .route("/demo/duplicates", get(list_demo_duplicates))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicatesQuery {
+    /// 省略時は `DUPLICATE_THRESHOLD`
+    pub threshold: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicatesResponse {
+    pub clusters: Vec<DuplicateCluster>,
+    pub total: usize,
+}
+
+async fn list_duplicates(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Query(query): Query<DuplicatesQuery>,
+) -> Result<Json<DuplicatesResponse>> {
+    let is_demo = !auth.is_authenticated;
+    run_duplicates(&state, query, is_demo).await.map(Json)
+}
+
+async fn list_demo_duplicates(
+    State(state): State<AppState>,
+    Query(query): Query<DuplicatesQuery>,
+) -> Result<Json<DuplicatesResponse>> {
+    run_duplicates(&state, query, true).await.map(Json)
+}
+
+async fn run_duplicates(
+    state: &AppState,
+    query: DuplicatesQuery,
+    demo: bool,
+) -> Result<DuplicatesResponse> {
+    if let Some(threshold) = query.threshold {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(AppError::BadRequest(
+                "threshold must be between 0 and 1".into(),
+            ));
+        }
+    }
+
+    let clusters = state.duplicates.clusters(query.threshold, demo).await?;
+    Ok(DuplicatesResponse {
+        total: clusters.len(),
+        clusters,
+    })
+}
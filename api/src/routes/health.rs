@@ -1,4 +1,4 @@
-use axum::{routing::get, Json, Router};
+use axum::{extract::State, routing::get, Json, Router};
 use serde_json::{json, Value};
 
 use crate::AppState;
@@ -7,9 +7,10 @@ pub fn routes() -> Router<AppState> {
     Router::new().route("/health", get(health_check))
 }
 
-async fn health_check() -> Json<Value> {
+async fn health_check(State(state): State<AppState>) -> Json<Value> {
     Json(json!({
         "status": "ok",
-        "version": env!("CARGO_PKG_VERSION")
+        "version": env!("CARGO_PKG_VERSION"),
+        "embedder_available": state.embedder.is_available()
     }))
 }
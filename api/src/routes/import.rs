@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+
+use axum::{extract::State, routing::post, Json, Router};
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::auth::AuthState;
+use crate::error::{AppError, Result};
+use crate::models::{EmbeddingStatus, Memo, MemoType, Recurrence};
+use crate::services::{parse_calendar, ImportedEvent};
+use crate::AppState;
+
+use super::memo::{check_dates, check_recurrence, create_with_embedding, save_updated_memo};
+use super::require_auth;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/import/ics", post(import_ics))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedEvent {
+    pub uid: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    pub created: usize,
+    pub updated: usize,
+    /// 取り込み済みで内容に変化の無かったイベント
+    pub unchanged: usize,
+    pub skipped: Vec<SkippedEvent>,
+}
+
+/// 変換したイベントをどう保存したか
+enum Outcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// `.ics` の本文を受け取り、VEVENT ごとにフラッシュメモを作る。
+/// UID が取り込み済み (またはこのアプリが書き出したメモ) なら新しく作らずに上書きする
+async fn import_ics(
+    State(state): State<AppState>,
+    auth: AuthState,
+    body: String,
+) -> Result<Json<ImportResponse>> {
+    require_auth(&auth)?;
+
+    let events = parse_calendar(&body, state.calendar.timezone)?;
+    let mut imported = state.qdrant.imported_memos(false).await?;
+    let mut seen = HashSet::new();
+    let mut response = ImportResponse {
+        created: 0,
+        updated: 0,
+        unchanged: 0,
+        skipped: Vec::new(),
+    };
+
+    for event in events {
+        if let Some(uid) = &event.uid {
+            if !seen.insert(uid.clone()) {
+                response.skipped.push(SkippedEvent {
+                    uid: event.uid.clone(),
+                    reason: "Duplicate UID in the file".into(),
+                });
+                continue;
+            }
+        }
+
+        // 書き出したメモが削除済みでも、以前に同じ UID で取り込んだメモがあればそれを上書きする
+        let exported = match event.exported_memo_id() {
+            Some(id) => state.qdrant.get_memo(id, false).await?,
+            None => None,
+        };
+        let existing = exported.or_else(|| event.uid.as_ref().and_then(|uid| imported.remove(uid)));
+
+        match import_event(&state, &event, existing).await {
+            Ok(Outcome::Created) => response.created += 1,
+            Ok(Outcome::Updated) => response.updated += 1,
+            Ok(Outcome::Unchanged) => response.unchanged += 1,
+            Err(AppError::BadRequest(reason)) => response.skipped.push(SkippedEvent {
+                uid: event.uid.clone(),
+                reason,
+            }),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(Json(response))
+}
+
+/// イベント1件を保存する。イベント自体の問題で取り込めない場合は BadRequest (そのイベントだけ飛ばす)
+async fn import_event(
+    state: &AppState,
+    event: &ImportedEvent,
+    existing: Option<Memo>,
+) -> Result<Outcome> {
+    let content = event.content();
+    if content.is_empty() {
+        return Err(AppError::BadRequest(
+            "Event has neither SUMMARY nor DESCRIPTION".into(),
+        ));
+    }
+    let tags = state.tags.normalize(&event.categories)?;
+    let from = event.start.or(event.end);
+    let until = event.end.filter(|end| Some(*end) != from);
+    let timed = [from, until].iter().flatten().any(|d| d.time.is_some());
+    let recurrence = event
+        .rrule
+        .as_deref()
+        .map(str::parse::<Recurrence>)
+        .transpose()
+        .map_err(|e| AppError::BadRequest(format!("Unsupported RRULE: {}", e)))?;
+
+    let Some(mut memo) = existing else {
+        let now = Utc::now();
+        let memo = Memo {
+            id: Uuid::new_v4(),
+            content,
+            memo_type: MemoType::Flash,
+            from,
+            until,
+            timezone: event.timezone,
+            tags,
+            date_added: now,
+            access_count: 0,
+            last_accessed: now,
+            completed: false,
+            embedding_status: EmbeddingStatus::Pending,
+            ics_uid: event.uid.clone(),
+            recurrence,
+            completed_occurrences: Vec::new(),
+        };
+        let memo = check_dates(memo).and_then(check_recurrence)?;
+        create_with_embedding(state, memo, true, false).await?;
+        return Ok(Outcome::Created);
+    };
+
+    // 終日のイベントにはタイムゾーンが無いので、取り込み済みのメモのものを残す
+    let timezone = if timed { event.timezone } else { memo.timezone };
+    let content_changed = memo.content != content;
+    if !content_changed
+        && memo.from == from
+        && memo.until == until
+        && memo.timezone == timezone
+        && memo.tags == tags
+        && memo.recurrence == recurrence
+    {
+        return Ok(Outcome::Unchanged);
+    }
+
+    let old_tags = std::mem::replace(&mut memo.tags, tags);
+    memo.content = content;
+    memo.from = from;
+    memo.until = until;
+    memo.timezone = timezone;
+    memo.recurrence = recurrence;
+    let mut memo = check_dates(memo).and_then(check_recurrence)?;
+    save_updated_memo(state, &mut memo, &old_tags, content_changed).await?;
+    Ok(Outcome::Updated)
+}
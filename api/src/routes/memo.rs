@@ -1,61 +1,185 @@
 use axum::{
     extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
-use chrono::Utc;
+use axum_extra::extract::Query;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::auth::AuthState;
 use crate::error::{AppError, Result};
-use crate::models::{CreateMemoRequest, Memo, MemoResponse, UpdateMemoRequest};
+use crate::models::{
+    CreateMemoRequest, EmbeddingStatus, Memo, MemoDate, MemoResponse, MemoType, MergeMemosRequest,
+    SearchFilters, SearchResponse, UpdateMemoRequest,
+};
+use crate::services::DuplicateCandidate;
 use crate::AppState;
 
+use super::require_auth;
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/memo", post(create_memo))
         .route("/memo/{id}", get(get_memo))
         .route("/memo/{id}", put(update_memo))
         .route("/memo/{id}", delete(delete_memo))
+        .route("/memo/{id}/related", get(related_memos))
+        .route("/memo/{id}/complete", post(complete_memo))
+        .route("/memo/{id}/uncomplete", post(uncomplete_memo))
+        .route("/memos/pending", get(list_pending_memos))
+        .route("/memos/merge", post(merge_memos))
         // Demo routes (no auth required, use memos_demo collection)
         .route("/demo/memo", post(create_demo_memo))
         .route("/demo/memo/{id}", get(get_demo_memo))
+        .route("/demo/memo/{id}/related", get(related_demo_memos))
+}
+
+/// `GET /memo/{id}/related` のクエリ。`SearchFilters` の項目を `?tags=a&tags=b` 形式で受け取る
+#[derive(Debug, Deserialize)]
+pub struct RelatedQuery {
+    #[serde(flatten)]
+    pub filters: SearchFilters,
+    #[serde(default = "default_related_limit")]
+    pub limit: u32,
+}
+
+fn default_related_limit() -> u32 {
+    10
 }
 
-fn require_auth(auth: &AuthState) -> Result<()> {
-    if !auth.is_authenticated {
-        return Err(AppError::Unauthorized("Authentication required".into()));
+/// 関連メモの最大件数
+const MAX_RELATED_LIMIT: u32 = 100;
+
+impl RelatedQuery {
+    /// タグを正規化したフィルタ (クエリからは取り出す)
+    fn filters(&mut self, state: &AppState) -> Result<SearchFilters> {
+        let mut filters = std::mem::take(&mut self.filters);
+        filters.tags = state.tags.normalize(&filters.tags)?;
+        Ok(filters)
+    }
+
+    fn limit(&self) -> u32 {
+        self.limit.clamp(1, MAX_RELATED_LIMIT)
     }
-    Ok(())
 }
 
-async fn create_memo(
-    State(state): State<AppState>,
-    auth: AuthState,
-    Json(req): Json<CreateMemoRequest>,
-) -> Result<Json<MemoResponse>> {
-    require_auth(&auth)?;
+#[derive(Debug, Serialize)]
+pub struct DuplicatesFoundResponse {
+    pub error: String,
+    pub duplicates: Vec<DuplicateCandidate>,
+}
 
-    let now = Utc::now();
-    let id = Uuid::new_v4();
+/// ベクトル化してから保存する。`force` でなければ近傍を調べ、
+/// ほぼ同じ内容のメモがあれば保存せずに候補を 409 で返す。
+/// Embedderが停止中なら pending で保存してワーカーに任せ、202 を返す (重複チェックはしない)
+pub(super) async fn create_with_embedding(
+    state: &AppState,
+    mut memo: Memo,
+    force: bool,
+    demo: bool,
+) -> Result<Response> {
+    // 重複の確認が済むまでは保存しない (保留のまま保存すると埋め込みキューが拾ってしまう)
+    let embedded = state.embedder.embed_for_storage(&memo.content).await;
+    if let (Ok(vector), false) = (&embedded, force) {
+        let duplicates = state.duplicates.candidates(vector, demo).await?;
+        if !duplicates.is_empty() {
+            let body = DuplicatesFoundResponse {
+                error: format!("Found {} near-duplicate memos", duplicates.len()),
+                duplicates,
+            };
+            return Ok((StatusCode::CONFLICT, Json(body)).into_response());
+        }
+    }
 
-    let memo = Memo {
-        id,
-        content: req.content.clone(),
+    // ベクトル化はリトライで長くかかるので、タグの作り直しを待たせるのは保存からにする
+    let _tags = state.tags.write_guard().await;
+    let status = match embedded {
+        Ok(vector) => {
+            memo.embedding_status = EmbeddingStatus::Ready;
+            state.qdrant.insert_memo(&memo, vector, demo).await?;
+            StatusCode::OK
+        }
+        Err(e) => {
+            tracing::warn!("Deferring embedding of memo {}: {}", memo.id, e);
+            memo.embedding_status = EmbeddingStatus::Pending;
+            state.qdrant.insert_pending_memo(&memo, demo).await?;
+            state.embedding_queue.wake();
+            StatusCode::ACCEPTED
+        }
+    };
+
+    state.tags.track(&[], &memo.tags, demo).await;
+    Ok((status, Json(MemoResponse::from(memo))).into_response())
+}
+
+fn new_memo(state: &AppState, req: CreateMemoRequest) -> Result<Memo> {
+    let now = Utc::now();
+    Ok(Memo {
+        id: Uuid::new_v4(),
+        tags: state.tags.normalize(&req.tags)?,
+        content: req.content,
         memo_type: req.memo_type,
         from: req.from,
         until: req.until,
-        tags: req.tags,
+        timezone: req.timezone,
         date_added: now,
         access_count: 0,
         last_accessed: now,
         completed: false,
-    };
+        embedding_status: EmbeddingStatus::Pending,
+        ics_uid: None,
+        recurrence: req.recurrence,
+        completed_occurrences: Vec::new(),
+    })
+    .and_then(check_dates)
+    .and_then(check_recurrence)
+}
 
-    let vector = state.embedder.embed_for_storage(&req.content).await?;
-    state.qdrant.insert_memo(&memo, vector, false).await?;
+/// 日付は MemoDate の範囲の年に限る
+pub(super) fn check_dates(memo: Memo) -> Result<Memo> {
+    for date in [memo.from, memo.until].into_iter().flatten() {
+        if !date.is_supported() {
+            return Err(AppError::BadRequest(format!(
+                "{} must be between {} and {}",
+                date,
+                MemoDate::MIN_YEAR,
+                MemoDate::MAX_YEAR
+            )));
+        }
+    }
+    Ok(memo)
+}
 
-    Ok(Json(memo.into()))
+/// 繰り返しメモには初回 (from) が必要。ルールや初回が変わって系列に無くなった回の完了は捨てる
+pub(super) fn check_recurrence(mut memo: Memo) -> Result<Memo> {
+    match (&memo.recurrence, memo.from) {
+        (None, _) => memo.completed_occurrences.clear(),
+        (Some(_), None) => {
+            return Err(AppError::BadRequest(
+                "A recurring memo needs a from date (the first occurrence)".into(),
+            ))
+        }
+        (Some(recurrence), Some(from)) => memo
+            .completed_occurrences
+            .retain(|date| recurrence.is_occurrence(from.date, *date)),
+    }
+    Ok(memo)
+}
+
+async fn create_memo(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Json(req): Json<CreateMemoRequest>,
+) -> Result<Response> {
+    require_auth(&auth)?;
+
+    let force = req.force;
+    let memo = new_memo(&state, req)?;
+    create_with_embedding(&state, memo, force, false).await
 }
 
 async fn get_memo(
@@ -89,6 +213,8 @@ async fn update_memo(
         .ok_or_else(|| AppError::NotFound(format!("Memo {} not found", id)))?;
 
     let content_changed = req.content.is_some() && req.content.as_ref() != Some(&memo.content);
+    let newly_completed = req.completed == Some(true) && !memo.completed;
+    let old_tags = memo.tags.clone();
 
     if let Some(content) = req.content {
         memo.content = content;
@@ -102,24 +228,274 @@ async fn update_memo(
     if let Some(until) = req.until {
         memo.until = Some(until);
     }
+    if let Some(timezone) = req.timezone {
+        memo.timezone = timezone;
+    }
     if let Some(tags) = req.tags {
-        memo.tags = tags;
+        memo.tags = state.tags.normalize(&tags)?;
     }
     if let Some(completed) = req.completed {
         memo.completed = completed;
     }
+    if let Some(recurrence) = req.recurrence {
+        memo.recurrence = recurrence;
+    }
 
+    let mut memo = check_dates(memo).and_then(check_recurrence)?;
     memo.last_accessed = Utc::now();
 
+    save_updated_memo(&state, &mut memo, &old_tags, content_changed).await?;
+    if newly_completed {
+        archive_if_completed(&state, &memo).await?;
+    }
+
+    Ok(Json(memo.into()))
+}
+
+/// 書き換えたメモを保存する。本文が変わっていれば再ベクトル化し、
+/// Embedderが停止中なら pending にしてワーカーに任せる
+pub(super) async fn save_updated_memo(
+    state: &AppState,
+    memo: &mut Memo,
+    old_tags: &[String],
+    content_changed: bool,
+) -> Result<()> {
     let vector = if content_changed {
-        Some(state.embedder.embed_for_storage(&memo.content).await?)
+        match state.embedder.embed_for_storage(&memo.content).await {
+            Ok(vector) => {
+                memo.embedding_status = EmbeddingStatus::Ready;
+                Some(vector)
+            }
+            Err(AppError::EmbedderUnavailable(reason)) => {
+                tracing::warn!("Deferring embedding of memo {}: {}", memo.id, reason);
+                memo.embedding_status = EmbeddingStatus::Pending;
+                None
+            }
+            Err(e) => return Err(e),
+        }
     } else {
         None
     };
 
-    state.qdrant.update_memo(&memo, vector, false).await?;
+    let _tags = state.tags.write_guard().await;
+    state.qdrant.update_memo(memo, vector, false).await?;
+    state.tags.track(old_tags, &memo.tags, false).await;
+    if memo.embedding_status == EmbeddingStatus::Pending {
+        state.embedding_queue.wake();
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionResponse {
+    #[serde(flatten)]
+    pub memo: MemoResponse,
+    /// `ARCHIVE_ON_COMPLETE` により memos_archive に移した場合は true
+    pub archived: bool,
+}
 
-    Ok(Json(memo.into()))
+#[derive(Debug, Deserialize)]
+pub struct CompletionQuery {
+    /// 繰り返しメモの回 (その回の初日)。省略すると系列全体
+    pub occurrence: Option<NaiveDate>,
+}
+
+async fn complete_memo(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CompletionQuery>,
+) -> Result<Json<CompletionResponse>> {
+    require_auth(&auth)?;
+    set_completed(&state, id, true, query.occurrence)
+        .await
+        .map(Json)
+}
+
+async fn uncomplete_memo(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CompletionQuery>,
+) -> Result<Json<CompletionResponse>> {
+    require_auth(&auth)?;
+    set_completed(&state, id, false, query.occurrence)
+        .await
+        .map(Json)
+}
+
+/// 完了フラグだけを書き換える (再ベクトル化しない)。
+/// `occurrence` を指定した場合はその回だけで、系列全体の完了フラグは変えない
+async fn set_completed(
+    state: &AppState,
+    id: Uuid,
+    completed: bool,
+    occurrence: Option<NaiveDate>,
+) -> Result<CompletionResponse> {
+    let mut memo = state
+        .qdrant
+        .get_memo(id, false)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Memo {} not found", id)))?;
+
+    if let Some(date) = occurrence {
+        let (Some(recurrence), Some(from)) = (&memo.recurrence, memo.from) else {
+            return Err(AppError::BadRequest(format!(
+                "Memo {} is not recurring",
+                id
+            )));
+        };
+        if !recurrence.is_occurrence(from.date, date) {
+            return Err(AppError::BadRequest(format!(
+                "{} is not an occurrence of memo {}",
+                date, id
+            )));
+        }
+
+        memo.completed_occurrences.retain(|d| *d != date);
+        if completed {
+            memo.completed_occurrences.push(date);
+            memo.completed_occurrences.sort();
+        }
+        state
+            .qdrant
+            .set_completed_occurrences(id, &memo.completed_occurrences, false)
+            .await?;
+        return Ok(CompletionResponse {
+            memo: memo.into(),
+            archived: false,
+        });
+    }
+
+    memo.completed = completed;
+    state
+        .qdrant
+        .set_memo_completed(id, completed, false)
+        .await?;
+    let archived = archive_if_completed(state, &memo).await?;
+
+    Ok(CompletionResponse {
+        memo: memo.into(),
+        archived,
+    })
+}
+
+/// `ARCHIVE_ON_COMPLETE` が有効なら、完了したメモを memos_archive に移す。移した場合は true
+async fn archive_if_completed(state: &AppState, memo: &Memo) -> Result<bool> {
+    if !memo.completed || !state.archive_on_complete {
+        return Ok(false);
+    }
+
+    let _tags = state.tags.write_guard().await;
+    state.qdrant.archive_memo(memo.id, false).await?;
+    state.tags.track(&memo.tags, &[], false).await;
+    Ok(true)
+}
+
+async fn list_pending_memos(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Json<Vec<MemoResponse>>> {
+    require_auth(&auth)?;
+
+    let memos = state.qdrant.list_pending_memos(false).await?;
+    Ok(Json(memos.into_iter().map(Into::into).collect()))
+}
+
+/// 複数のメモを1つにまとめる。本文は作成日時順に `separator` で連結し、タグは和集合、
+/// 期間は最も早い from から最も遅い until まで、アクセス数は合計する
+async fn merge_memos(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Json(req): Json<MergeMemosRequest>,
+) -> Result<Response> {
+    require_auth(&auth)?;
+
+    let mut ids = req.ids;
+    ids.sort();
+    ids.dedup();
+    if ids.len() < 2 {
+        return Err(AppError::BadRequest(
+            "At least two distinct memos are required".into(),
+        ));
+    }
+
+    let mut sources = Vec::with_capacity(ids.len());
+    for id in &ids {
+        let memo = state
+            .qdrant
+            .get_memo(*id, false)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Memo {} not found", id)))?;
+        sources.push(memo);
+    }
+    sources.sort_by_key(|m| m.date_added);
+
+    let merged = merge_into_new(&sources, &req.separator);
+    let merged_id = merged.id;
+    tracing::info!("Merging {} memos into {}", sources.len(), merged_id);
+
+    // 統合後のメモを先に保存してから元のメモを消す。元のメモとの重複チェックは不要
+    let response = create_with_embedding(&state, merged, true, false).await?;
+
+    for source in &sources {
+        // リマインダーは統合後のメモで通知する
+        state.reminders.move_to_memo(source.id, merged_id).await?;
+        let _tags = state.tags.write_guard().await;
+        if req.archive_sources {
+            state.qdrant.archive_memo(source.id, false).await?;
+        } else {
+            state.qdrant.delete_memo(source.id, false).await?;
+        }
+        state.tags.track(&source.tags, &[], false).await;
+    }
+
+    Ok(response)
+}
+
+fn merge_into_new(sources: &[Memo], separator: &str) -> Memo {
+    let now = Utc::now();
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in sources.iter().flat_map(|m| &m.tags) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    // どれか1つでも永続メモなら永続メモとして残す
+    let memo_type = if sources.iter().any(|m| m.memo_type == MemoType::Permanent) {
+        MemoType::Permanent
+    } else {
+        MemoType::Flash
+    };
+
+    Memo {
+        id: Uuid::new_v4(),
+        content: sources
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join(separator),
+        memo_type,
+        from: sources.iter().filter_map(|m| m.from).min(),
+        // 終日の終わりは同じ日のどの時刻よりも後
+        until: sources
+            .iter()
+            .filter_map(|m| m.until)
+            .max_by_key(|d| (d.date, d.time.is_none(), d.time)),
+        timezone: sources.iter().find_map(|m| m.timezone),
+        tags,
+        date_added: sources.iter().map(|m| m.date_added).min().unwrap_or(now),
+        access_count: sources.iter().map(|m| m.access_count).sum(),
+        last_accessed: sources.iter().map(|m| m.last_accessed).max().unwrap_or(now),
+        completed: sources.iter().all(|m| m.completed),
+        embedding_status: EmbeddingStatus::Pending,
+        ics_uid: None,
+        // 繰り返しは引き継がない (統合後は from〜until の1つの期間になる)
+        recurrence: None,
+        completed_occurrences: Vec::new(),
+    }
 }
 
 async fn delete_memo(
@@ -129,34 +505,23 @@ async fn delete_memo(
 ) -> Result<Json<()>> {
     require_auth(&auth)?;
 
+    let _tags = state.tags.write_guard().await;
+    let memo = state.qdrant.get_memo(id, false).await?;
     state.qdrant.delete_memo(id, false).await?;
+    state.reminders.delete_for_memo(id).await?;
+    if let Some(memo) = memo {
+        state.tags.track(&memo.tags, &[], false).await;
+    }
     Ok(Json(()))
 }
 
 async fn create_demo_memo(
     State(state): State<AppState>,
     Json(req): Json<CreateMemoRequest>,
-) -> Result<Json<MemoResponse>> {
-    let now = Utc::now();
-    let id = Uuid::new_v4();
-
-    let memo = Memo {
-        id,
-        content: req.content.clone(),
-        memo_type: req.memo_type,
-        from: req.from,
-        until: req.until,
-        tags: req.tags,
-        date_added: now,
-        access_count: 0,
-        last_accessed: now,
-        completed: false,
-    };
-
-    let vector = state.embedder.embed_for_storage(&req.content).await?;
-    state.qdrant.insert_memo(&memo, vector, true).await?;
-
-    Ok(Json(memo.into()))
+) -> Result<Response> {
+    let force = req.force;
+    let memo = new_memo(&state, req)?;
+    create_with_embedding(&state, memo, force, true).await
 }
 
 async fn get_demo_memo(
@@ -171,3 +536,71 @@ async fn get_demo_memo(
 
     Ok(Json(memo.into()))
 }
+
+async fn related_memos(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Path(id): Path<Uuid>,
+    Query(mut query): Query<RelatedQuery>,
+) -> Result<Json<SearchResponse>> {
+    require_auth(&auth)?;
+
+    let results = state
+        .qdrant
+        .related_memos(id, &query.filters(&state)?, query.limit(), false)
+        .await?;
+    Ok(Json(SearchResponse {
+        total: results.len(),
+        results,
+        degraded: false,
+        interpretation: None,
+    }))
+}
+
+async fn related_demo_memos(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(mut query): Query<RelatedQuery>,
+) -> Result<Json<SearchResponse>> {
+    let results = state
+        .qdrant
+        .related_memos(id, &query.filters(&state)?, query.limit(), true)
+        .await?;
+    Ok(Json(SearchResponse {
+        total: results.len(),
+        results,
+        degraded: false,
+        interpretation: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+
+    use super::*;
+    use crate::models::DateMatch;
+
+    fn related_query(query: &str) -> RelatedQuery {
+        let uri: Uri = format!("/memo/x/related?{}", query).parse().unwrap();
+        Query::<RelatedQuery>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn related_query_reads_search_filters() {
+        let query = related_query(
+            "tags=a&type=flash&completed=false&from_gte=2026-01-01&date_match=contained&limit=5",
+        );
+        assert_eq!(query.filters.tags, vec!["a"]);
+        assert_eq!(query.filters.memo_type, Some(MemoType::Flash));
+        assert_eq!(query.filters.completed, Some(false));
+        assert_eq!(query.filters.from_gte, NaiveDate::from_ymd_opt(2026, 1, 1));
+        assert_eq!(query.filters.date_match, DateMatch::Contained);
+        assert_eq!(query.limit(), 5);
+
+        let query = related_query("tags=a&tags=b&limit=100000");
+        assert_eq!(query.filters.tags, vec!["a", "b"]);
+        assert_eq!(query.limit(), MAX_RELATED_LIMIT);
+        assert_eq!(related_query("").limit(), 10);
+    }
+}
@@ -1,16 +1,39 @@
+mod admin;
+mod agenda;
+mod calendar;
+mod clusters;
+mod duplicates;
 mod health;
+mod import;
 mod memo;
+mod reminders;
 mod search;
 mod tags;
 
 use axum::Router;
 
+use crate::auth::AuthState;
+use crate::error::{AppError, Result};
 use crate::AppState;
 
 pub fn create_router() -> Router<AppState> {
     Router::new()
+        .merge(admin::routes())
+        .merge(agenda::routes())
+        .merge(calendar::routes())
+        .merge(clusters::routes())
+        .merge(duplicates::routes())
         .merge(health::routes())
+        .merge(import::routes())
         .merge(memo::routes())
+        .merge(reminders::routes())
         .merge(search::routes())
         .merge(tags::routes())
 }
+
+fn require_auth(auth: &AuthState) -> Result<()> {
+    if !auth.is_authenticated {
+        return Err(AppError::Unauthorized("Authentication required".into()));
+    }
+    Ok(())
+}
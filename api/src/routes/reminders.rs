@@ -0,0 +1,106 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use axum_extra::extract::Query;
+use serde::Deserialize;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use uuid::Uuid;
+
+use crate::auth::AuthState;
+use crate::error::{AppError, Result};
+use crate::models::{CreateReminderRequest, Reminder, ReminderStatus};
+use crate::AppState;
+
+use super::require_auth;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/memo/{id}/reminders", post(create_reminder))
+        .route("/memo/{id}/reminders", get(memo_reminders))
+        .route("/reminders", get(list_reminders))
+        .route("/reminders/stream", get(reminder_stream))
+        .route("/reminders/{id}", delete(delete_reminder))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReminderQuery {
+    pub memo_id: Option<Uuid>,
+    pub status: Option<ReminderStatus>,
+}
+
+async fn create_reminder(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateReminderRequest>,
+) -> Result<Json<Reminder>> {
+    require_auth(&auth)?;
+
+    if state.qdrant.get_memo(id, false).await?.is_none() {
+        return Err(AppError::NotFound(format!("Memo {} not found", id)));
+    }
+    let reminder = state.reminders.create(id, req.remind_at).await?;
+    Ok(Json(reminder))
+}
+
+async fn memo_reminders(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Reminder>>> {
+    require_auth(&auth)?;
+    let reminders = state.reminders.list(Some(id), None).await?;
+    Ok(Json(reminders))
+}
+
+async fn list_reminders(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Query(query): Query<ReminderQuery>,
+) -> Result<Json<Vec<Reminder>>> {
+    require_auth(&auth)?;
+    let reminders = state.reminders.list(query.memo_id, query.status).await?;
+    Ok(Json(reminders))
+}
+
+async fn delete_reminder(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>> {
+    require_auth(&auth)?;
+    state.reminders.delete(id).await?;
+    Ok(Json(()))
+}
+
+/// 時刻になったリマインダーを `reminder` イベントで流す
+async fn reminder_stream(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    require_auth(&auth)?;
+
+    let receiver = state.reminders.subscribe().await?;
+    let events = BroadcastStream::new(receiver).filter_map(|notification| {
+        // 受信が遅れて取りこぼした分 (Lagged) は飛ばす。取りこぼしは GET /reminders で確認できる
+        let notification = notification.ok()?;
+        match Event::default().event("reminder").json_data(&notification) {
+            Ok(event) => Some(Ok(event)),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to encode reminder {}: {}",
+                    notification.reminder_id,
+                    e
+                );
+                None
+            }
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
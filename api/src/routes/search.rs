@@ -1,10 +1,15 @@
 use axum::{extract::State, routing::post, Json, Router};
 
 use crate::auth::AuthState;
-use crate::error::Result;
-use crate::models::{SearchRequest, SearchResponse};
+use crate::error::{AppError, Result};
+use crate::models::{SearchRequest, SearchResponse, SearchResult};
+use crate::services::{mmr_rerank, parse_query};
 use crate::AppState;
 
+/// MMRの候補として取得する件数 (limit の倍率と上限)
+const DIVERSITY_OVERFETCH: u32 = 4;
+const DIVERSITY_MAX_CANDIDATES: u32 = 100;
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/search", post(search))
@@ -17,31 +22,163 @@ async fn search(
     Json(req): Json<SearchRequest>,
 ) -> Result<Json<SearchResponse>> {
     let is_demo = !auth.is_authenticated;
-    
-    let vector = state.embedder.embed_for_search(&req.query).await?;
-    let results = state
-        .qdrant
-        .search(vector, &req.filters, req.limit, is_demo)
-        .await?;
-
-    Ok(Json(SearchResponse {
-        total: results.len(),
-        results,
-    }))
+    run_search(&state, req, is_demo).await.map(Json)
 }
 
 async fn demo_search(
     State(state): State<AppState>,
     Json(req): Json<SearchRequest>,
 ) -> Result<Json<SearchResponse>> {
-    let vector = state.embedder.embed_for_search(&req.query).await?;
-    let results = state
-        .qdrant
-        .search(vector, &req.filters, req.limit, true)
-        .await?;
+    run_search(&state, req, true).await.map(Json)
+}
+
+async fn run_search(
+    state: &AppState,
+    mut req: SearchRequest,
+    demo: bool,
+) -> Result<SearchResponse> {
+    let mut interpretation = parse_query(&req.query)?;
+    // 保存時と同じ規則で正規化しないと一致しない
+    interpretation.tags = state.tags.normalize(&interpretation.tags)?;
+    req.filters.tags = state.tags.normalize(&req.filters.tags)?;
+    interpretation.apply_to(&mut req.filters);
+    let query = interpretation.text.as_str();
+
+    if req.diversity.is_some_and(|d| !(0.0..=1.0).contains(&d)) {
+        return Err(AppError::BadRequest(
+            "diversity must be between 0 and 1".into(),
+        ));
+    }
+    let diversity = req.diversity.filter(|d| *d > 0.0);
 
-    Ok(Json(SearchResponse {
+    // 演算子だけのクエリはベクトル検索せず、条件に合うメモを新しい順に返す
+    if query.is_empty() {
+        let results = state
+            .qdrant
+            .filtered_memos(&req.filters, req.limit, demo)
+            .await?;
+        return Ok(SearchResponse {
+            total: results.len(),
+            results,
+            degraded: false,
+            interpretation: Some(interpretation),
+        });
+    }
+
+    let (results, degraded) = match state.embedder.embed_for_search(query).await {
+        Ok(vector) => {
+            let results = match diversity {
+                Some(diversity) => diversified_search(state, vector, &req, diversity, demo).await?,
+                None => {
+                    state
+                        .qdrant
+                        .search(vector, &req.filters, req.limit, demo)
+                        .await?
+                }
+            };
+            // ベクトル化待ちのメモは語彙一致で補う
+            let pending = state
+                .qdrant
+                .lexical_search(query, &req.filters, req.limit, demo, true)
+                .await?;
+            (append_pending(results, pending, req.limit as usize), false)
+        }
+        Err(AppError::EmbedderUnavailable(reason)) => {
+            tracing::warn!(
+                "Embedder unavailable ({}), falling back to lexical search",
+                reason
+            );
+            let results = state
+                .qdrant
+                .lexical_search(query, &req.filters, req.limit, demo, false)
+                .await?;
+            (results, true)
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(SearchResponse {
         total: results.len(),
         results,
-    }))
+        degraded,
+        interpretation: Some(interpretation),
+    })
+}
+
+/// 多めに取得してから MMR で limit 件に絞る
+async fn diversified_search(
+    state: &AppState,
+    vector: Vec<f32>,
+    req: &SearchRequest,
+    diversity: f32,
+    demo: bool,
+) -> Result<Vec<SearchResult>> {
+    let limit = req.limit.min(DIVERSITY_MAX_CANDIDATES);
+    let candidates = limit
+        .saturating_mul(DIVERSITY_OVERFETCH)
+        .clamp(limit, DIVERSITY_MAX_CANDIDATES);
+    let hits = state
+        .qdrant
+        .search_with_vectors(vector.clone(), &req.filters, candidates, demo)
+        .await?;
+
+    Ok(mmr_rerank(&vector, hits, diversity, limit as usize))
+}
+
+/// 語彙一致のスコア (一致した語の割合) は類似度と比べられないので混ぜて並べず、
+/// ベクトル検索の結果の後ろに付け足す。ベクトル検索で埋まっても出るよう、最大で半分の枠を取っておく
+fn append_pending(
+    mut results: Vec<SearchResult>,
+    mut pending: Vec<SearchResult>,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let reserved = pending.len().min(limit.div_ceil(2));
+    results.truncate(limit - reserved);
+    pending.truncate(limit - results.len());
+    results.extend(pending);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn results(prefix: &str, count: usize) -> Vec<SearchResult> {
+        (0..count)
+            .map(|i| SearchResult {
+                id: Uuid::new_v4(),
+                content: format!("{}{}", prefix, i),
+                score: 1.0,
+                tags: Vec::new(),
+                from: None,
+                date_added: Utc::now(),
+                completed: false,
+            })
+            .collect()
+    }
+
+    fn contents(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.content.as_str()).collect()
+    }
+
+    #[test]
+    fn pending_hits_keep_slots_when_vector_results_fill_the_limit() {
+        let merged = append_pending(results("v", 4), results("p", 1), 4);
+        assert_eq!(contents(&merged), vec!["v0", "v1", "v2", "p0"]);
+
+        // 取っておくのは最大で半分まで
+        let merged = append_pending(results("v", 5), results("p", 5), 5);
+        assert_eq!(contents(&merged), vec!["v0", "v1", "p0", "p1", "p2"]);
+    }
+
+    #[test]
+    fn unused_slots_go_to_the_other_results() {
+        let merged = append_pending(results("v", 5), Vec::new(), 4);
+        assert_eq!(contents(&merged), vec!["v0", "v1", "v2", "v3"]);
+
+        let merged = append_pending(results("v", 1), results("p", 5), 4);
+        assert_eq!(contents(&merged), vec!["v0", "p0", "p1", "p2"]);
+    }
 }
@@ -1,20 +1,41 @@
-use axum::{extract::State, routing::get, Json, Router};
-use serde::Serialize;
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::auth::AuthState;
 use crate::error::Result;
+use crate::models::{SearchFilters, SearchResult};
+use crate::services::{TagEntry, TagRewrite, TagSuggestion};
 use crate::AppState;
 
+use super::require_auth;
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/tags", get(list_tags))
+        .route("/tags/recommend", post(recommend_tags))
+        .route("/tags/rebuild", post(rebuild_tags))
+        .route("/tags/rename", post(rename_tag))
+        .route("/tags/merge", post(merge_tags))
+        .route("/tags/suggest", get(suggest_tags))
         .route("/demo/tags", get(list_demo_tags))
+        .route("/demo/tags/suggest", get(suggest_demo_tags))
+        .route("/demo/tags/recommend", post(recommend_demo_tags))
 }
 
 #[derive(Debug, Serialize)]
 pub struct TagNode {
     pub name: String,
     pub path: String,
+    /// このタグがそのまま付いているメモの数
+    pub memo_count: u64,
+    /// 子孫タグを含めたメモの数 (重複なし)
+    pub subtree_memo_count: u64,
     pub children: Vec<TagNode>,
 }
 
@@ -23,27 +44,258 @@ pub struct TagsResponse {
     pub tags: Vec<TagNode>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct RebuildTagsResponse {
+    pub tags: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestQuery {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default = "default_suggest_limit")]
+    pub limit: usize,
+}
+
+fn default_suggest_limit() -> usize {
+    10
+}
+
+const MAX_SUGGEST_LIMIT: usize = 50;
+
+#[derive(Debug, Serialize)]
+pub struct SuggestResponse {
+    pub suggestions: Vec<TagSuggestion>,
+}
+
+/// 推薦に使う近傍メモの件数
+const RECOMMEND_NEIGHBOURS: u32 = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct RecommendTagsRequest {
+    pub content: String,
+    #[serde(default = "default_recommend_limit")]
+    pub limit: usize,
+}
+
+fn default_recommend_limit() -> usize {
+    5
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagRecommendation {
+    pub path: String,
+    /// 0-1。類似メモの中でこのタグが占める重みの割合 × 最も近いメモの類似度
+    pub confidence: f32,
+    /// このタグが付いていた類似メモの数
+    pub memo_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecommendTagsResponse {
+    pub recommendations: Vec<TagRecommendation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameTagRequest {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeTagsRequest {
+    pub sources: Vec<String>,
+    pub target: String,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagRewriteResponse {
+    pub dry_run: bool,
+    pub total: usize,
+    pub memos: Vec<TagRewrite>,
+}
+
 async fn list_tags(State(state): State<AppState>, auth: AuthState) -> Result<Json<TagsResponse>> {
     let is_demo = !auth.is_authenticated;
-    let tags = state.qdrant.get_all_tags(is_demo).await?;
-    let tree = build_tag_tree(tags);
+    let entries = state.tags.list(is_demo).await?;
+    let tree = build_tag_tree(entries);
     Ok(Json(TagsResponse { tags: tree }))
 }
 
 async fn list_demo_tags(State(state): State<AppState>) -> Result<Json<TagsResponse>> {
-    let tags = state.qdrant.get_all_tags(true).await?;
-    let tree = build_tag_tree(tags);
+    let entries = state.tags.list(true).await?;
+    let tree = build_tag_tree(entries);
     Ok(Json(TagsResponse { tags: tree }))
 }
 
-fn build_tag_tree(tags: Vec<String>) -> Vec<TagNode> {
-    use std::collections::HashMap;
+async fn suggest_tags(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Query(query): Query<SuggestQuery>,
+) -> Result<Json<SuggestResponse>> {
+    let is_demo = !auth.is_authenticated;
+    let suggestions = state
+        .tags
+        .suggest(&query.q, query.limit.min(MAX_SUGGEST_LIMIT), is_demo)
+        .await?;
+    Ok(Json(SuggestResponse { suggestions }))
+}
+
+async fn suggest_demo_tags(
+    State(state): State<AppState>,
+    Query(query): Query<SuggestQuery>,
+) -> Result<Json<SuggestResponse>> {
+    let suggestions = state
+        .tags
+        .suggest(&query.q, query.limit.min(MAX_SUGGEST_LIMIT), true)
+        .await?;
+    Ok(Json(SuggestResponse { suggestions }))
+}
+
+async fn recommend_tags(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Json(req): Json<RecommendTagsRequest>,
+) -> Result<Json<RecommendTagsResponse>> {
+    let is_demo = !auth.is_authenticated;
+    run_recommend(&state, req, is_demo).await.map(Json)
+}
+
+async fn recommend_demo_tags(
+    State(state): State<AppState>,
+    Json(req): Json<RecommendTagsRequest>,
+) -> Result<Json<RecommendTagsResponse>> {
+    run_recommend(&state, req, true).await.map(Json)
+}
+
+async fn run_recommend(
+    state: &AppState,
+    req: RecommendTagsRequest,
+    demo: bool,
+) -> Result<RecommendTagsResponse> {
+    if req.content.trim().is_empty() {
+        return Ok(RecommendTagsResponse {
+            recommendations: Vec::new(),
+        });
+    }
+
+    // 保存済みメモと同じ形でベクトル化して近傍を探す
+    let vector = state.embedder.embed_for_storage(&req.content).await?;
+    let neighbours = state
+        .qdrant
+        .search(
+            vector,
+            &SearchFilters::default(),
+            RECOMMEND_NEIGHBOURS,
+            demo,
+        )
+        .await?;
+
+    let mut recommendations = aggregate_tags(&neighbours);
+    recommendations.truncate(req.limit);
+    Ok(RecommendTagsResponse { recommendations })
+}
+
+/// 近傍メモのタグを類似度で重み付けして集計する
+fn aggregate_tags(neighbours: &[SearchResult]) -> Vec<TagRecommendation> {
+    struct Tally {
+        weight: f32,
+        best: f32,
+        memo_count: usize,
+    }
+
+    let total: f32 = neighbours.iter().map(|n| n.score).sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut tallies: HashMap<&str, Tally> = HashMap::new();
+    for neighbour in neighbours.iter().filter(|n| n.score > 0.0) {
+        for tag in &neighbour.tags {
+            let tally = tallies.entry(tag.as_str()).or_insert(Tally {
+                weight: 0.0,
+                best: 0.0,
+                memo_count: 0,
+            });
+            tally.weight += neighbour.score;
+            tally.best = tally.best.max(neighbour.score);
+            tally.memo_count += 1;
+        }
+    }
+
+    let mut recommendations: Vec<TagRecommendation> = tallies
+        .into_iter()
+        .map(|(path, tally)| TagRecommendation {
+            path: path.to_string(),
+            confidence: (tally.weight / total) * tally.best,
+            memo_count: tally.memo_count,
+        })
+        .collect();
+    recommendations.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    recommendations
+}
+
+async fn rebuild_tags(
+    State(state): State<AppState>,
+    auth: AuthState,
+) -> Result<Json<RebuildTagsResponse>> {
+    require_auth(&auth)?;
+
+    let count = state.tags.rebuild(false).await?;
+    Ok(Json(RebuildTagsResponse { tags: count }))
+}
+
+async fn rename_tag(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Json(req): Json<RenameTagRequest>,
+) -> Result<Json<TagRewriteResponse>> {
+    require_auth(&auth)?;
+
+    let memos = state
+        .tags
+        .rewrite(&[req.from], &req.to, req.dry_run, false)
+        .await?;
+    Ok(Json(TagRewriteResponse {
+        dry_run: req.dry_run,
+        total: memos.len(),
+        memos,
+    }))
+}
+
+async fn merge_tags(
+    State(state): State<AppState>,
+    auth: AuthState,
+    Json(req): Json<MergeTagsRequest>,
+) -> Result<Json<TagRewriteResponse>> {
+    require_auth(&auth)?;
+
+    let memos = state
+        .tags
+        .rewrite(&req.sources, &req.target, req.dry_run, false)
+        .await?;
+    Ok(Json(TagRewriteResponse {
+        dry_run: req.dry_run,
+        total: memos.len(),
+        memos,
+    }))
+}
 
+fn build_tag_tree(entries: Vec<TagEntry>) -> Vec<TagNode> {
+    let counts: HashMap<&str, &TagEntry> = entries.iter().map(|e| (e.path.as_str(), e)).collect();
     let mut root: HashMap<String, TagNode> = HashMap::new();
 
-    for tag in tags {
-        let parts: Vec<&str> = tag.split('/').collect();
-        insert_tag_path(&mut root, &parts, 0, &tag);
+    for entry in &entries {
+        let parts: Vec<&str> = entry.path.split('/').collect();
+        insert_tag_path(&mut root, &parts, 0, &counts);
     }
 
     let mut result: Vec<TagNode> = root.into_values().collect();
@@ -52,10 +304,10 @@ fn build_tag_tree(tags: Vec<String>) -> Vec<TagNode> {
 }
 
 fn insert_tag_path(
-    nodes: &mut std::collections::HashMap<String, TagNode>,
+    nodes: &mut HashMap<String, TagNode>,
     parts: &[&str],
     depth: usize,
-    full_path: &str,
+    counts: &HashMap<&str, &TagEntry>,
 ) {
     if depth >= parts.len() {
         return;
@@ -64,20 +316,25 @@ fn insert_tag_path(
     let name = parts[depth].to_string();
     let path_so_far = parts[..=depth].join("/");
 
-    let node = nodes.entry(name.clone()).or_insert_with(|| TagNode {
-        name: name.clone(),
-        path: path_so_far.clone(),
-        children: Vec::new(),
+    let node = nodes.entry(name.clone()).or_insert_with(|| {
+        let entry = counts.get(path_so_far.as_str());
+        TagNode {
+            name: name.clone(),
+            path: path_so_far.clone(),
+            memo_count: entry.map(|e| e.usage_count).unwrap_or(0),
+            subtree_memo_count: entry.map(|e| e.subtree_count).unwrap_or(0),
+            children: Vec::new(),
+        }
     });
 
     if depth + 1 < parts.len() {
-        let mut child_map: std::collections::HashMap<String, TagNode> = node
+        let mut child_map: HashMap<String, TagNode> = node
             .children
             .drain(..)
             .map(|c| (c.name.clone(), c))
             .collect();
 
-        insert_tag_path(&mut child_map, parts, depth + 1, full_path);
+        insert_tag_path(&mut child_map, parts, depth + 1, counts);
 
         node.children = child_map.into_values().collect();
         node.children.sort_by(|a, b| a.name.cmp(&b.name));
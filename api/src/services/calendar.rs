@@ -0,0 +1,275 @@
+use chrono::{
+    Datelike, Days, LocalResult, Months, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+
+use crate::models::{DatePreset, MemoDate, SearchFilters};
+
+/// 日付フィルタの基準 (既定タイムゾーンと週の始まり)。
+/// 日付だけのメモは暦日で、時刻付きのメモはメモのタイムゾーン (無ければ既定) での瞬間で比べる
+#[derive(Debug, Clone, Copy)]
+pub struct Calendar {
+    pub timezone: Tz,
+    /// 週の始まり (this_week / next_week の境界)
+    pub week_start: Weekday,
+}
+
+impl Calendar {
+    pub fn today(&self, timezone: Tz) -> NaiveDate {
+        Utc::now().with_timezone(&timezone).date_naive()
+    }
+
+    /// プリセットを `timezone` での日付区間 (両端を含む) に直す
+    pub fn preset_range(&self, preset: DatePreset, timezone: Tz) -> (NaiveDate, NaiveDate) {
+        self.preset_range_from(preset, self.today(timezone))
+    }
+
+    /// プリセットを `today` を基準にした日付区間 (両端を含む) に直す
+    pub fn preset_range_from(
+        &self,
+        preset: DatePreset,
+        today: NaiveDate,
+    ) -> (NaiveDate, NaiveDate) {
+        match preset {
+            DatePreset::Today => (today, today),
+            DatePreset::Tomorrow => {
+                let tomorrow = today + Days::new(1);
+                (tomorrow, tomorrow)
+            }
+            DatePreset::ThisWeek => self.week_of(today),
+            DatePreset::NextWeek => self.week_of(today + Days::new(7)),
+            DatePreset::ThisMonth => {
+                let first = today.with_day(1).expect("day 1 always exists");
+                let last = first + Months::new(1) - Days::new(1);
+                (first, last)
+            }
+        }
+    }
+
+    /// フィルタの日付条件を検索区間に直す。プリセットより from_gte / until_lte の明示を優先する
+    pub fn filter_window(&self, filters: &SearchFilters) -> DateWindow {
+        let timezone = filters.timezone.unwrap_or(self.timezone);
+        let preset = filters
+            .preset
+            .map(|preset| self.preset_range(preset, timezone));
+
+        DateWindow {
+            start: filters.from_gte.or(preset.map(|(from, _)| from)),
+            end: filters.until_lte.or(preset.map(|(_, until)| until)),
+            timezone,
+        }
+    }
+
+    fn week_of(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let start = date.week(self.week_start).first_day();
+        (start, start + Days::new(6))
+    }
+}
+
+/// 日付フィルタの検索区間 (両端を含む、どちらも省略可)。
+/// 日付だけのメモは暦日 (`day_number`) で、時刻付きのメモは `timezone` での瞬間 (UNIX秒) で比べる
+#[derive(Debug, Clone, Copy)]
+pub struct DateWindow {
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+    pub timezone: Tz,
+}
+
+impl DateWindow {
+    pub fn is_unbounded(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
+
+    /// 区間の始まりの暦日
+    pub fn start_day(&self) -> Option<i64> {
+        self.start.map(day_number)
+    }
+
+    /// 区間の終わりの暦日
+    pub fn end_day(&self) -> Option<i64> {
+        self.end.map(day_number)
+    }
+
+    /// 区間の始まり (`timezone` の0時) のUNIX秒
+    pub fn start_ts(&self) -> Option<i64> {
+        self.start.map(|date| day_start(date, self.timezone))
+    }
+
+    /// 区間の終わり (`timezone` の23:59:59) のUNIX秒
+    pub fn end_ts(&self) -> Option<i64> {
+        self.end.map(|date| day_end(date, self.timezone))
+    }
+}
+
+/// タイムゾーンに依存しない暦日の通し番号 (payload の `from_day` / `until_day`)
+pub fn day_number(date: NaiveDate) -> i64 {
+    date.num_days_from_ce() as i64
+}
+
+/// `date` の0時 (`timezone`) のUNIX秒
+pub fn day_start(date: NaiveDate, timezone: Tz) -> i64 {
+    local_timestamp(date.and_hms_opt(0, 0, 0).unwrap(), timezone)
+}
+
+/// `date` の23:59:59 (`timezone`) のUNIX秒
+pub fn day_end(date: NaiveDate, timezone: Tz) -> i64 {
+    day_start(date + Days::new(1), timezone) - 1
+}
+
+/// メモの日付の始まりのUNIX秒。時刻が無ければその日の0時
+pub fn start_timestamp(date: MemoDate, timezone: Tz) -> i64 {
+    match date.time {
+        Some(time) => local_timestamp(date.date.and_time(time), timezone),
+        None => day_start(date.date, timezone),
+    }
+}
+
+/// メモの日付の終わりのUNIX秒。時刻が無ければその日の23:59:59
+pub fn end_timestamp(date: MemoDate, timezone: Tz) -> i64 {
+    match date.time {
+        Some(time) => local_timestamp(date.date.and_time(time), timezone),
+        None => day_end(date.date, timezone),
+    }
+}
+
+fn local_timestamp(local: NaiveDateTime, timezone: Tz) -> i64 {
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t.timestamp(),
+        // 夏時間の切り替えで存在しない時刻は、1時間後にずらす
+        LocalResult::None => local_timestamp(local + TimeDelta::hours(1), timezone),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn calendar(week_start: Weekday) -> Calendar {
+        Calendar {
+            timezone: Tz::UTC,
+            week_start,
+        }
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn day_presets() {
+        let calendar = calendar(Weekday::Mon);
+        let today = date(2026, 12, 31);
+        assert_eq!(
+            calendar.preset_range_from(DatePreset::Today, today),
+            (today, today)
+        );
+        assert_eq!(
+            calendar.preset_range_from(DatePreset::Tomorrow, today),
+            (date(2027, 1, 1), date(2027, 1, 1))
+        );
+    }
+
+    #[test]
+    fn month_preset_covers_the_whole_month() {
+        let calendar = calendar(Weekday::Mon);
+        assert_eq!(
+            calendar.preset_range_from(DatePreset::ThisMonth, date(2028, 2, 29)),
+            (date(2028, 2, 1), date(2028, 2, 29))
+        );
+        assert_eq!(
+            calendar.preset_range_from(DatePreset::ThisMonth, date(2026, 12, 15)),
+            (date(2026, 12, 1), date(2026, 12, 31))
+        );
+    }
+
+    #[test]
+    fn week_presets_follow_week_start() {
+        // 2026-01-04 は日曜
+        let sunday = date(2026, 1, 4);
+        assert_eq!(
+            calendar(Weekday::Mon).preset_range_from(DatePreset::ThisWeek, sunday),
+            (date(2025, 12, 29), date(2026, 1, 4))
+        );
+        assert_eq!(
+            calendar(Weekday::Sun).preset_range_from(DatePreset::ThisWeek, sunday),
+            (date(2026, 1, 4), date(2026, 1, 10))
+        );
+        assert_eq!(
+            calendar(Weekday::Mon).preset_range_from(DatePreset::NextWeek, sunday),
+            (date(2026, 1, 5), date(2026, 1, 11))
+        );
+        assert_eq!(
+            calendar(Weekday::Sun).preset_range_from(DatePreset::NextWeek, sunday),
+            (date(2026, 1, 11), date(2026, 1, 17))
+        );
+    }
+
+    #[test]
+    fn explicit_dates_override_the_preset() {
+        let calendar = calendar(Weekday::Mon);
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        let filters = SearchFilters {
+            from_gte: Some(date(2026, 1, 10)),
+            preset: Some(DatePreset::Today),
+            timezone: Some(tokyo),
+            ..Default::default()
+        };
+        let window = calendar.filter_window(&filters);
+        let today = calendar.today(tokyo);
+        assert_eq!(window.start, Some(date(2026, 1, 10)));
+        assert_eq!(window.end, Some(today));
+        assert_eq!(window.timezone, tokyo);
+        assert_eq!(window.start_ts(), Some(utc(2026, 1, 9, 15, 0)));
+        assert_eq!(window.start_day(), Some(day_number(date(2026, 1, 10))));
+        assert!(calendar
+            .filter_window(&SearchFilters::default())
+            .is_unbounded());
+    }
+
+    #[test]
+    fn day_bounds_across_dst_changes() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        // 夏時間の終わり (1:00〜2:00 が2回ある) は25時間
+        let fall_back = date(2026, 11, 1);
+        assert_eq!(day_start(fall_back, new_york), utc(2026, 11, 1, 4, 0));
+        assert_eq!(day_end(fall_back, new_york) + 1, utc(2026, 11, 2, 5, 0));
+        // 夏時間の始まりは23時間
+        let spring_forward = date(2026, 3, 8);
+        assert_eq!(
+            day_end(spring_forward, new_york) - day_start(spring_forward, new_york) + 1,
+            23 * 3600
+        );
+    }
+
+    #[test]
+    fn ambiguous_and_skipped_local_times() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        let at = |d: NaiveDate, h: u32, m: u32| MemoDate {
+            date: d,
+            time: chrono::NaiveTime::from_hms_opt(h, m, 0),
+        };
+        // 2回ある 1:30 は早い方 (夏時間)
+        assert_eq!(
+            start_timestamp(at(date(2026, 11, 1), 1, 30), new_york),
+            utc(2026, 11, 1, 5, 30)
+        );
+        // 存在しない 2:30 は1時間後の 3:30 (夏時間)
+        assert_eq!(
+            start_timestamp(at(date(2026, 3, 8), 2, 30), new_york),
+            utc(2026, 3, 8, 7, 30)
+        );
+        // 0時が存在しない日は 1:00 から始まる
+        let sao_paulo: Tz = "America/Sao_Paulo".parse().unwrap();
+        assert_eq!(
+            day_start(date(2018, 11, 4), sao_paulo),
+            utc(2018, 11, 4, 3, 0)
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// HalfOpen は試行中。`until` までに結果が記録されなければ (試行したリクエストが中断されたなど) 次の試行を許可する
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { until: Instant },
+}
+
+/// 連続失敗回数がしきい値を超えたら一定時間リクエストを遮断する
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    state: Arc<Mutex<BreakerState>>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BreakerState::Closed { failures: 0 })),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+        }
+    }
+
+    /// リクエストを通してよいか判定する。クールダウン経過後は1件だけ試行を許可する
+    pub fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } | BreakerState::HalfOpen { until } if now >= until => {
+                *state = BreakerState::HalfOpen {
+                    until: now + self.cooldown,
+                };
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.state.lock().unwrap(), BreakerState::Closed { .. })
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if *state != (BreakerState::Closed { failures: 0 }) {
+            tracing::info!("Embedder circuit breaker closed");
+        }
+        *state = BreakerState::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            BreakerState::Closed { failures } => failures + 1,
+            BreakerState::HalfOpen { .. } => self.failure_threshold,
+            BreakerState::Open { .. } => return,
+        };
+
+        *state = if failures >= self.failure_threshold {
+            tracing::warn!(
+                "Embedder circuit breaker opened for {}s",
+                self.cooldown.as_secs()
+            );
+            BreakerState::Open {
+                until: Instant::now() + self.cooldown,
+            }
+        } else {
+            BreakerState::Closed { failures }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.record_failure();
+        assert!(breaker.allow_request());
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        assert!(!breaker.allow_request());
+    }
+
+    #[test]
+    fn half_open_allows_a_single_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow_request());
+        assert!(!breaker.allow_request());
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow_request());
+    }
+
+    #[test]
+    fn abandoned_probe_does_not_block_forever() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(60));
+        // 試行を許可されたリクエストが結果を記録せずに終わった
+        assert!(breaker.allow_request());
+        assert!(!breaker.allow_request());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow_request());
+    }
+}
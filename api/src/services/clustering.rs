@@ -0,0 +1,507 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::error::{AppError, Result};
+use crate::models::{Memo, MemoResponse};
+use crate::services::vector_math::{dot, normalize};
+use crate::services::QdrantService;
+
+const MAX_ITERATIONS: usize = 30;
+/// 初期中心の選び方の乱数のシード。同じメモなら毎回同じクラスタになるよう固定する
+const SEED: u64 = 0x5eed;
+const REPRESENTATIVES: usize = 3;
+const DOMINANT_TAGS: usize = 3;
+/// この割合以上のメモに付いているタグを「主なタグ」とする
+const MIN_TAG_SHARE: f32 = 0.2;
+/// 過半数に付いているタグが無いクラスタはタグ未整理とみなし、新しいタグを提案する
+const TAGGED_SHARE: f32 = 0.5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterMemo {
+    #[serde(flatten)]
+    pub memo: MemoResponse,
+    /// クラスタ中心とのコサイン類似度
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterTag {
+    pub path: String,
+    /// クラスタ内でこのタグが付いているメモの割合
+    pub share: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoCluster {
+    pub size: usize,
+    /// 中心に近い順
+    pub representatives: Vec<ClusterMemo>,
+    pub dominant_tags: Vec<ClusterTag>,
+    /// タグ未整理のクラスタに付ける新しいタグの候補 (本文の頻出語)
+    pub suggested_tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterReport {
+    pub clusters: Vec<MemoCluster>,
+    pub memo_count: usize,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// ベクトル化済みのメモを球面k-meansでまとめるバックグラウンドジョブ。結果はメモリに保持する
+#[derive(Clone)]
+pub struct ClusteringJob {
+    qdrant: QdrantService,
+    /// 0 ならメモ数から自動で決める
+    cluster_count: usize,
+    reports: Arc<RwLock<HashMap<bool, ClusterReport>>>,
+}
+
+impl ClusteringJob {
+    pub fn start(qdrant: QdrantService, cluster_count: usize, interval: Duration) -> Self {
+        let job = Self {
+            qdrant,
+            cluster_count,
+            reports: Arc::new(RwLock::new(HashMap::new())),
+        };
+
+        let worker = job.clone();
+        tokio::spawn(async move {
+            loop {
+                for demo in [false, true] {
+                    if let Err(e) = worker.run(demo).await {
+                        tracing::warn!("Clustering failed: {}", e);
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+
+        job
+    }
+
+    /// 直近の結果。まだ計算していなければその場で計算する
+    pub async fn report(&self, demo: bool) -> Result<ClusterReport> {
+        if let Some(report) = self.reports.read().unwrap().get(&demo) {
+            return Ok(report.clone());
+        }
+        self.run(demo).await
+    }
+
+    pub async fn run(&self, demo: bool) -> Result<ClusterReport> {
+        let memos = self.qdrant.scroll_memo_vectors(demo).await?;
+        let k = match self.cluster_count {
+            0 => ((memos.len() as f64 / 2.0).sqrt().round() as usize).clamp(2, 30),
+            k => k,
+        };
+
+        let report = tokio::task::spawn_blocking(move || build_report(memos, k))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        tracing::info!(
+            "Clustered {} memos into {} clusters{}",
+            report.memo_count,
+            report.clusters.len(),
+            if demo { " (demo)" } else { "" }
+        );
+        self.reports.write().unwrap().insert(demo, report.clone());
+        Ok(report)
+    }
+}
+
+fn build_report(memos: Vec<(Memo, Vec<f32>)>, k: usize) -> ClusterReport {
+    let memo_count = memos.len();
+    let (memos, vectors): (Vec<Memo>, Vec<Vec<f32>>) = memos
+        .into_iter()
+        .map(|(memo, vector)| (memo, normalize(vector)))
+        .unzip();
+
+    if memos.len() < 2 {
+        return ClusterReport {
+            clusters: Vec::new(),
+            memo_count,
+            computed_at: Utc::now(),
+        };
+    }
+
+    let k = k.min(memos.len());
+    let (assignments, centroids) = kmeans(&vectors, k, &mut StdRng::seed_from_u64(SEED));
+    let document_frequency = term_document_frequency(&memos);
+
+    let mut clusters: Vec<MemoCluster> = (0..centroids.len())
+        .filter_map(|c| {
+            let members: Vec<usize> = (0..memos.len()).filter(|&i| assignments[i] == c).collect();
+            if members.is_empty() {
+                return None;
+            }
+            Some(describe_cluster(
+                &members,
+                &memos,
+                &vectors,
+                &centroids[c],
+                &document_frequency,
+            ))
+        })
+        .collect();
+
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.size));
+    ClusterReport {
+        clusters,
+        memo_count,
+        computed_at: Utc::now(),
+    }
+}
+
+fn describe_cluster(
+    members: &[usize],
+    memos: &[Memo],
+    vectors: &[Vec<f32>],
+    centroid: &[f32],
+    document_frequency: &HashMap<String, usize>,
+) -> MemoCluster {
+    let mut by_similarity: Vec<(usize, f32)> = members
+        .iter()
+        .map(|&i| (i, dot(&vectors[i], centroid)))
+        .collect();
+    by_similarity.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let representatives = by_similarity
+        .iter()
+        .take(REPRESENTATIVES)
+        .map(|&(i, similarity)| ClusterMemo {
+            memo: memos[i].clone().into(),
+            similarity,
+        })
+        .collect();
+
+    let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+    for &i in members {
+        for tag in &memos[i].tags {
+            *tag_counts.entry(tag.as_str()).or_default() += 1;
+        }
+    }
+    let mut dominant_tags: Vec<ClusterTag> = tag_counts
+        .into_iter()
+        .map(|(path, count)| ClusterTag {
+            path: path.to_string(),
+            share: count as f32 / members.len() as f32,
+        })
+        .filter(|t| t.share >= MIN_TAG_SHARE)
+        .collect();
+    dominant_tags.sort_by(|a, b| {
+        b.share
+            .total_cmp(&a.share)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    dominant_tags.truncate(DOMINANT_TAGS);
+
+    let is_tagged = dominant_tags
+        .first()
+        .is_some_and(|t| t.share >= TAGGED_SHARE);
+    let suggested_tag = if is_tagged {
+        None
+    } else {
+        suggest_label(members, memos, document_frequency)
+    };
+
+    MemoCluster {
+        size: members.len(),
+        representatives,
+        dominant_tags,
+        suggested_tag,
+    }
+}
+
+/// k-means++ で初期化した球面k-means。各ベクトルの所属クラスタと中心を返す
+fn kmeans(vectors: &[Vec<f32>], k: usize, rng: &mut impl Rng) -> (Vec<usize>, Vec<Vec<f32>>) {
+    let mut centroids = init_centroids(vectors, k, rng);
+    // 同じベクトルばかりだと k 個の中心が取れないことがある
+    let k = centroids.len();
+    let mut assignments = vec![usize::MAX; vectors.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (i, vector) in vectors.iter().enumerate() {
+            let nearest = nearest_centroid(vector, &centroids);
+            if assignments[i] != nearest {
+                assignments[i] = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let dimension = vectors[0].len();
+        let mut sums = vec![vec![0.0_f32; dimension]; k];
+        for (vector, &c) in vectors.iter().zip(&assignments) {
+            for (sum, x) in sums[c].iter_mut().zip(vector) {
+                *sum += x;
+            }
+        }
+        for (c, sum) in sums.into_iter().enumerate() {
+            // 空になったクラスタは前の中心を残す
+            if sum.iter().any(|x| *x != 0.0) {
+                centroids[c] = normalize(sum);
+            }
+        }
+    }
+
+    (assignments, centroids)
+}
+
+fn init_centroids(vectors: &[Vec<f32>], k: usize, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+    let mut centroids = vec![vectors[rng.random_range(0..vectors.len())].clone()];
+
+    while centroids.len() < k {
+        // 既存の中心から遠い (コサイン距離の2乗が大きい) ものほど選ばれやすくする
+        let weights: Vec<f32> = vectors
+            .iter()
+            .map(|v| {
+                let distance = 1.0 - dot(v, &centroids[nearest_centroid(v, &centroids)]);
+                distance.max(0.0).powi(2)
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+
+        let mut target = rng.random::<f32>() * total;
+        let mut chosen = vectors.len() - 1;
+        for (i, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                chosen = i;
+                break;
+            }
+            target -= weight;
+        }
+        centroids.push(vectors[chosen].clone());
+    }
+
+    centroids
+}
+
+fn nearest_centroid(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(c, centroid)| (c, dot(vector, centroid)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(c, _)| c)
+        .unwrap_or(0)
+}
+
+/// クラスタ内で多くのメモに出現し、全体ではあまり出現しない語をタグ候補にする
+fn suggest_label(
+    members: &[usize],
+    memos: &[Memo],
+    document_frequency: &HashMap<String, usize>,
+) -> Option<String> {
+    let mut cluster_frequency: HashMap<String, usize> = HashMap::new();
+    for &i in members {
+        for term in terms(&memos[i].content) {
+            *cluster_frequency.entry(term).or_default() += 1;
+        }
+    }
+
+    let total = memos.len() as f32;
+    cluster_frequency
+        .into_iter()
+        .filter(|(_, count)| *count >= 2)
+        .map(|(term, count)| {
+            let df = document_frequency.get(&term).copied().unwrap_or(count) as f32;
+            // 長い語を少し優先する (「歯医」より「歯医者」)
+            let length_bonus = 1.0 + 0.1 * term.chars().count() as f32;
+            let score = count as f32 * (total / df).ln_1p() * length_bonus;
+            (term, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(term, _)| term)
+}
+
+fn term_document_frequency(memos: &[Memo]) -> HashMap<String, usize> {
+    let mut frequency = HashMap::new();
+    for memo in memos {
+        for term in terms(&memo.content) {
+            *frequency.entry(term).or_default() += 1;
+        }
+    }
+    frequency
+}
+
+/// 本文から候補語を取り出す (メモ内で重複なし)。
+/// 英数字は空白・記号区切りの単語、分かち書きされない日本語は2〜4文字のN-gramにする
+fn terms(content: &str) -> HashSet<String> {
+    let mut terms = HashSet::new();
+
+    for token in content
+        .split(|c: char| c.is_whitespace() || (c.is_ascii_punctuation()) || is_cjk_punctuation(c))
+        .filter(|t| !t.is_empty())
+    {
+        if token.is_ascii() {
+            if token.len() >= 3 {
+                terms.insert(token.to_lowercase());
+            }
+            continue;
+        }
+
+        let chars: Vec<char> = token.chars().collect();
+        for n in 2..=4 {
+            for window in chars.windows(n) {
+                terms.insert(window.iter().collect());
+            }
+        }
+    }
+
+    terms
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '、' | '。' | '「' | '」' | '（' | '）' | '・' | '！' | '？' | '：'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memo(content: &str, tags: &[&str]) -> Memo {
+        Memo {
+            id: uuid::Uuid::new_v4(),
+            content: content.to_string(),
+            memo_type: crate::models::MemoType::Flash,
+            from: None,
+            until: None,
+            timezone: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            date_added: Utc::now(),
+            access_count: 0,
+            last_accessed: Utc::now(),
+            completed: false,
+            embedding_status: Default::default(),
+            ics_uid: None,
+            recurrence: None,
+            completed_occurrences: Vec::new(),
+        }
+    }
+
+    fn unit(degrees: f32) -> Vec<f32> {
+        let radians = degrees.to_radians();
+        vec![radians.cos(), radians.sin()]
+    }
+
+    /// 0度付近と90度付近の2つの塊
+    fn two_groups() -> Vec<Vec<f32>> {
+        [0.0, 3.0, 6.0, 84.0, 87.0, 90.0]
+            .iter()
+            .map(|d| unit(*d))
+            .collect()
+    }
+
+    #[test]
+    fn kmeans_separates_clear_groups() {
+        let vectors = two_groups();
+        for seed in 0..20 {
+            let (assignments, centroids) = kmeans(&vectors, 2, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(centroids.len(), 2);
+            assert!(assignments[..3].iter().all(|&c| c == assignments[0]));
+            assert!(assignments[3..].iter().all(|&c| c == assignments[3]));
+            assert_ne!(assignments[0], assignments[3]);
+        }
+    }
+
+    #[test]
+    fn kmeans_is_deterministic_for_a_seed() {
+        let vectors: Vec<Vec<f32>> = (0..30).map(|i| unit(i as f32 * 7.0)).collect();
+        let run = || kmeans(&vectors, 4, &mut StdRng::seed_from_u64(SEED));
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn kmeans_takes_fewer_centroids_than_distinct_vectors() {
+        // 同じベクトルばかりなら k 個の中心は取れない
+        let vectors = vec![unit(0.0), unit(0.0), unit(90.0), unit(90.0)];
+        let (assignments, centroids) = kmeans(&vectors, 4, &mut StdRng::seed_from_u64(SEED));
+        assert_eq!(centroids.len(), 2);
+        assert!(assignments.iter().all(|&c| c < centroids.len()));
+    }
+
+    #[test]
+    fn report_with_k_larger_than_memo_count() {
+        let memos: Vec<(Memo, Vec<f32>)> = [0.0, 45.0, 90.0]
+            .iter()
+            .map(|d| (memo("メモ", &[]), unit(*d)))
+            .collect();
+        let report = build_report(memos, 10);
+        assert_eq!(report.memo_count, 3);
+        assert!(report.clusters.len() <= 3);
+        assert_eq!(report.clusters.iter().map(|c| c.size).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn report_drops_empty_clusters() {
+        // 中心が3つ取れても、塊は2つなので空のクラスタが出うる
+        let memos: Vec<(Memo, Vec<f32>)> = two_groups()
+            .into_iter()
+            .map(|v| (memo("メモ", &[]), v))
+            .collect();
+        let report = build_report(memos, 5);
+        assert!(report.clusters.iter().all(|c| c.size > 0));
+        assert_eq!(report.clusters.iter().map(|c| c.size).sum::<usize>(), 6);
+        // 大きい順
+        assert!(report.clusters.windows(2).all(|w| w[0].size >= w[1].size));
+    }
+
+    #[test]
+    fn report_needs_two_memos() {
+        let report = build_report(vec![(memo("メモ", &[]), unit(0.0))], 2);
+        assert_eq!(report.memo_count, 1);
+        assert!(report.clusters.is_empty());
+    }
+
+    #[test]
+    fn suggest_label_prefers_terms_specific_to_the_cluster() {
+        let memos = vec![
+            memo("歯医者の予約", &[]),
+            memo("歯医者に行く", &[]),
+            memo("買い物に行く", &[]),
+            memo("散歩に行く", &[]),
+        ];
+        let frequency = term_document_frequency(&memos);
+        assert_eq!(
+            suggest_label(&[0, 1], &memos, &frequency).as_deref(),
+            Some("歯医者")
+        );
+    }
+
+    #[test]
+    fn suggest_label_needs_a_shared_term() {
+        let memos = vec![memo("歯医者", &[]), memo("買い物", &[])];
+        let frequency = term_document_frequency(&memos);
+        assert_eq!(suggest_label(&[0, 1], &memos, &frequency), None);
+    }
+
+    #[test]
+    fn tagged_clusters_get_no_suggestion() {
+        let memos = vec![
+            memo("歯医者の予約", &["健康"]),
+            memo("歯医者に行く", &["健康"]),
+        ];
+        let vectors = vec![unit(0.0), unit(1.0)];
+        let frequency = term_document_frequency(&memos);
+        let cluster = describe_cluster(&[0, 1], &memos, &vectors, &unit(0.5), &frequency);
+        assert_eq!(cluster.dominant_tags[0].path, "健康");
+        assert_eq!(cluster.dominant_tags[0].share, 1.0);
+        assert_eq!(cluster.suggested_tag, None);
+    }
+}
@@ -0,0 +1,589 @@
+use qdrant_client::qdrant::{Condition, Filter, Range};
+
+use crate::models::DateMatch;
+use crate::services::calendar::DateWindow;
+
+/// 1日だけの予定か (from と until が同じ日か、片方しかない)。`single_day` モードで使う
+pub const SINGLE_DAY: &str = "single_day";
+/// 繰り返しメモか。繰り返しメモの from_ts / until_ts は系列全体 (初回〜最終回) を表す
+pub const RECURRING: &str = "recurring";
+
+/// 日付も時刻も無いメモ (from / until のどちらも日付だけ) か。こうしたメモは暦日で比べる
+pub const ALL_DAY: &str = "all_day";
+/// 日付だけのメモの初日・最終日の暦日 (`day_number`)。繰り返しメモの until_day は系列の最終回の最終日
+pub const FROM_DAY: &str = "from_day";
+pub const UNTIL_DAY: &str = "until_day";
+
+/// 期間の初日・最終日を持つ payload フィールドの組
+#[derive(Clone, Copy)]
+struct Fields {
+    first: &'static str,
+    last: &'static str,
+}
+
+/// 日付だけのメモ: 暦日
+const DAYS: Fields = Fields {
+    first: FROM_DAY,
+    last: UNTIL_DAY,
+};
+/// 時刻付きのメモ: UNIX秒
+const INSTANTS: Fields = Fields {
+    first: "from_ts",
+    last: "until_ts",
+};
+
+/// メモの期間と検索区間 `window` の比較条件。
+/// 日付だけのメモは暦日どうしで比べるので、メモを保存したときのタイムゾーンと区間のタイムゾーンが違っても日がずれない。
+/// 時刻付きのメモは `window.timezone` での区間と瞬間で比べる。
+/// 片方の日付しかないメモはその1日だけの予定として扱う
+/// (from だけなら from の日、until だけなら until の日)。日付の無いメモは一致しない。
+/// 繰り返しメモは系列の期間が区間と重なるかだけを見るので、各回の判定は取得後に行う
+pub fn date_conditions(mode: DateMatch, window: &DateWindow) -> Vec<Condition> {
+    if window.is_unbounded() {
+        return Vec::new();
+    }
+
+    let single = Filter {
+        must: vec![by_kind(window, |fields, start, end| {
+            single_conditions(mode, fields, start, end)
+        })],
+        must_not: vec![Condition::matches(RECURRING, true)],
+        ..Default::default()
+    };
+    let series = Filter::must([
+        Condition::matches(RECURRING, true),
+        by_kind(window, |fields, start, end| {
+            series_conditions(mode, fields, start, end)
+        }),
+    ]);
+    vec![Filter::should([single.into(), series.into()]).into()]
+}
+
+/// 日付だけのメモは暦日で、それ以外のメモは瞬間で `conditions` を組み立てる
+fn by_kind(
+    window: &DateWindow,
+    conditions: impl Fn(Fields, Option<i64>, Option<i64>) -> Vec<Condition>,
+) -> Condition {
+    let all_day = Condition::matches(ALL_DAY, true);
+    let mut by_day = vec![all_day.clone()];
+    by_day.extend(conditions(DAYS, window.start_day(), window.end_day()));
+    let by_instant = Filter {
+        must: conditions(INSTANTS, window.start_ts(), window.end_ts()),
+        must_not: vec![all_day],
+        ..Default::default()
+    };
+    Filter::should([Filter::must(by_day).into(), by_instant.into()]).into()
+}
+
+/// 繰り返しでないメモの条件
+fn single_conditions(
+    mode: DateMatch,
+    fields: Fields,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Vec<Condition> {
+    let conditions = match mode {
+        DateMatch::Overlaps => vec![
+            end.map(|end| first_day(fields, lte(end))),
+            start.map(|start| last_day(fields, gte(start))),
+        ],
+        DateMatch::Contained => vec![
+            start.map(|start| first_day(fields, gte(start))),
+            end.map(|end| last_day(fields, lte(end))),
+        ],
+        DateMatch::StartsWithin => vec![
+            start.map(|start| first_day(fields, gte(start))),
+            end.map(|end| first_day(fields, lte(end))),
+        ],
+        DateMatch::SingleDay => vec![
+            Some(Condition::matches(SINGLE_DAY, true)),
+            start.map(|start| first_day(fields, gte(start))),
+            end.map(|end| first_day(fields, lte(end))),
+        ],
+    };
+
+    conditions.into_iter().flatten().collect()
+}
+
+/// 繰り返しメモの条件。初回が区間の終わり以前で、最終回 (無ければ無期限) が区間の始まり以降
+fn series_conditions(
+    mode: DateMatch,
+    fields: Fields,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Vec<Condition> {
+    let conditions = vec![
+        (mode == DateMatch::SingleDay).then(|| Condition::matches(SINGLE_DAY, true)),
+        end.map(|end| Condition::range(fields.first, lte(end))),
+        start.map(|start| {
+            Filter::should([
+                Condition::is_empty(fields.last),
+                Condition::range(fields.last, gte(start)),
+            ])
+            .into()
+        }),
+    ];
+
+    conditions.into_iter().flatten().collect()
+}
+
+/// `date_conditions` と同じ判定を、日付の決まった1つの期間 `[first, last]` に対して行う
+/// (繰り返しメモの各回の判定用)。区間と期間は同じ単位 (暦日か UNIX秒) で渡す
+pub fn span_matches(
+    mode: DateMatch,
+    start: Option<i64>,
+    end: Option<i64>,
+    first: i64,
+    last: i64,
+    single_day: bool,
+) -> bool {
+    let after_start = |value: i64| start.is_none_or(|start| value >= start);
+    let before_end = |value: i64| end.is_none_or(|end| value <= end);
+    match mode {
+        DateMatch::Overlaps => before_end(first) && after_start(last),
+        DateMatch::Contained => after_start(first) && before_end(last),
+        DateMatch::StartsWithin => after_start(first) && before_end(first),
+        DateMatch::SingleDay => single_day && after_start(first) && before_end(first),
+    }
+}
+
+/// 初日 (from の日、無ければ until の日) の条件
+fn first_day(fields: Fields, range: Range) -> Condition {
+    either(fields.first, fields.last, range)
+}
+
+/// 最終日 (until の日、無ければ from の日) の条件
+fn last_day(fields: Fields, range: Range) -> Condition {
+    either(fields.last, fields.first, range)
+}
+
+/// `primary` が条件を満たすか、`primary` が無く `fallback` が条件を満たす (両方無ければ不一致)。
+/// 片方しかないメモは1日 (時刻付きなら1時点) の予定なので、初日と最終日を読み替えられる
+fn either(primary: &str, fallback: &str, range: Range) -> Condition {
+    Filter::should([
+        Condition::range(primary, range),
+        Filter::must([
+            Condition::is_empty(primary),
+            Condition::range(fallback, range),
+        ])
+        .into(),
+    ])
+    .into()
+}
+
+fn gte(ts: i64) -> Range {
+    Range {
+        gte: Some(ts as f64),
+        ..Default::default()
+    }
+}
+
+fn lte(ts: i64) -> Range {
+    Range {
+        lte: Some(ts as f64),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use chrono_tz::Tz;
+    use qdrant_client::qdrant::{
+        condition::ConditionOneOf, r#match::MatchValue, FieldCondition, Filter,
+    };
+
+    use super::*;
+    use crate::models::MemoDate;
+    use crate::services::calendar::{
+        day_end, day_number, day_start, end_timestamp, start_timestamp,
+    };
+
+    /// payload上のメモ (日付のフィールドと single_day / recurring / all_day だけ)
+    #[derive(Default)]
+    struct Dated {
+        from_ts: Option<i64>,
+        until_ts: Option<i64>,
+        from_day: Option<i64>,
+        until_day: Option<i64>,
+        single_day: bool,
+        recurring: bool,
+        all_day: bool,
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    /// 日付だけのメモ。from_ts / until_ts は UTC の日付境界で保存されている
+    fn memo(from: Option<u32>, until: Option<u32>) -> Dated {
+        Dated {
+            from_ts: from.map(|d| day_start(date(d), Tz::UTC)),
+            until_ts: until.map(|d| day_end(date(d), Tz::UTC)),
+            from_day: from.map(|d| day_number(date(d))),
+            until_day: until.map(|d| day_number(date(d))),
+            single_day: match (from, until) {
+                (Some(f), Some(u)) => f == u,
+                (None, None) => false,
+                _ => true,
+            },
+            recurring: false,
+            all_day: from.is_some() || until.is_some(),
+        }
+    }
+
+    /// 日付だけの繰り返しメモ。`last` は最終回の最終日 (無ければ無期限)
+    fn series(first: u32, last: Option<u32>) -> Dated {
+        Dated {
+            from_ts: Some(day_start(date(first), Tz::UTC)),
+            until_ts: last.map(|d| day_end(date(d), Tz::UTC)),
+            from_day: Some(day_number(date(first))),
+            until_day: last.map(|d| day_number(date(d))),
+            single_day: true,
+            recurring: true,
+            all_day: true,
+        }
+    }
+
+    fn window(start: Option<u32>, end: Option<u32>, timezone: Tz) -> DateWindow {
+        DateWindow {
+            start: start.map(date),
+            end: end.map(date),
+            timezone,
+        }
+    }
+
+    /// UTC の1月 `start` 日〜 `end` 日の区間で絞り込んだときに一致するか
+    fn matches(mode: DateMatch, start: Option<u32>, end: Option<u32>, memo: &Dated) -> bool {
+        matches_in(mode, &window(start, end, Tz::UTC), memo)
+    }
+
+    fn matches_in(mode: DateMatch, window: &DateWindow, memo: &Dated) -> bool {
+        let conditions = date_conditions(mode, window);
+        conditions.iter().all(|c| evaluate(c, memo))
+    }
+
+    /// Qdrantのフィルタ評価のうち、date_conditions が使う部分だけを再現する
+    fn evaluate(condition: &Condition, memo: &Dated) -> bool {
+        match condition.condition_one_of.as_ref().unwrap() {
+            ConditionOneOf::Filter(filter) => evaluate_filter(filter, memo),
+            ConditionOneOf::IsEmpty(c) => field(memo, &c.key).is_none(),
+            ConditionOneOf::Field(c) => evaluate_field(c, memo),
+            other => panic!("unexpected condition {:?}", other),
+        }
+    }
+
+    fn evaluate_filter(filter: &Filter, memo: &Dated) -> bool {
+        filter.must.iter().all(|c| evaluate(c, memo))
+            && !filter.must_not.iter().any(|c| evaluate(c, memo))
+            && (filter.should.is_empty() || filter.should.iter().any(|c| evaluate(c, memo)))
+    }
+
+    fn evaluate_field(condition: &FieldCondition, memo: &Dated) -> bool {
+        if let Some(actual) = flag(memo, &condition.key) {
+            let expected = match condition.r#match.as_ref().unwrap().match_value {
+                Some(MatchValue::Boolean(b)) => b,
+                ref other => panic!("unexpected match {:?}", other),
+            };
+            return actual == expected;
+        }
+
+        let Some(value) = field(memo, &condition.key) else {
+            return false;
+        };
+        let range = condition.range.as_ref().unwrap();
+        let value = value as f64;
+        range.gte.is_none_or(|gte| value >= gte) && range.lte.is_none_or(|lte| value <= lte)
+    }
+
+    /// 真偽値のフィールド。false のフラグは payload に無いのと同じ扱い (must_not で判定するため)
+    fn flag(memo: &Dated, key: &str) -> Option<bool> {
+        match key {
+            SINGLE_DAY => Some(memo.single_day),
+            RECURRING => Some(memo.recurring),
+            ALL_DAY => Some(memo.all_day),
+            _ => None,
+        }
+    }
+
+    fn field(memo: &Dated, key: &str) -> Option<i64> {
+        match key {
+            "from_ts" => memo.from_ts,
+            "until_ts" => memo.until_ts,
+            FROM_DAY => memo.from_day,
+            UNTIL_DAY => memo.until_day,
+            _ => panic!("unexpected field {}", key),
+        }
+    }
+
+    #[test]
+    fn no_bounds_adds_no_conditions() {
+        assert!(date_conditions(DateMatch::Overlaps, &window(None, None, Tz::UTC)).is_empty());
+    }
+
+    #[test]
+    fn undated_memos_never_match() {
+        let undated = memo(None, None);
+        for mode in [
+            DateMatch::Overlaps,
+            DateMatch::Contained,
+            DateMatch::StartsWithin,
+            DateMatch::SingleDay,
+        ] {
+            assert!(!matches(mode, Some(10), Some(16), &undated));
+            assert!(!matches(mode, Some(10), None, &undated));
+            assert!(!matches(mode, None, Some(16), &undated));
+        }
+    }
+
+    #[test]
+    fn overlaps_includes_spanning_and_partial_memos() {
+        let mode = DateMatch::Overlaps;
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(5), Some(20))));
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(5), Some(10))));
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(16), Some(20))));
+        assert!(!matches(mode, Some(10), Some(16), &memo(Some(1), Some(9))));
+        assert!(!matches(
+            mode,
+            Some(10),
+            Some(16),
+            &memo(Some(17), Some(20))
+        ));
+    }
+
+    #[test]
+    fn overlaps_treats_single_date_memos_as_one_day() {
+        let mode = DateMatch::Overlaps;
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(12), None)));
+        assert!(matches(mode, Some(10), Some(16), &memo(None, Some(16))));
+        assert!(!matches(mode, Some(10), Some(16), &memo(Some(9), None)));
+        assert!(!matches(mode, Some(10), Some(16), &memo(None, Some(17))));
+    }
+
+    #[test]
+    fn overlaps_with_open_ended_window() {
+        let mode = DateMatch::Overlaps;
+        // 10日以降
+        assert!(matches(mode, Some(10), None, &memo(Some(1), Some(12))));
+        assert!(matches(mode, Some(10), None, &memo(Some(30), None)));
+        assert!(matches(mode, Some(10), None, &memo(None, Some(10))));
+        assert!(!matches(mode, Some(10), None, &memo(Some(1), Some(9))));
+        assert!(!matches(mode, Some(10), None, &memo(Some(9), None)));
+        // 16日まで
+        assert!(matches(mode, None, Some(16), &memo(Some(15), Some(31))));
+        assert!(matches(mode, None, Some(16), &memo(Some(1), None)));
+        assert!(matches(mode, None, Some(16), &memo(None, Some(3))));
+        assert!(!matches(mode, None, Some(16), &memo(Some(17), None)));
+        assert!(!matches(mode, None, Some(16), &memo(None, Some(17))));
+    }
+
+    #[test]
+    fn contained_requires_the_whole_span_inside() {
+        let mode = DateMatch::Contained;
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(11), Some(12))));
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(10), Some(16))));
+        assert!(!matches(mode, Some(10), Some(16), &memo(Some(5), Some(20))));
+        assert!(!matches(
+            mode,
+            Some(10),
+            Some(16),
+            &memo(Some(12), Some(17))
+        ));
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(12), None)));
+        assert!(matches(mode, Some(10), Some(16), &memo(None, Some(12))));
+    }
+
+    #[test]
+    fn contained_with_open_ended_window() {
+        let mode = DateMatch::Contained;
+        // from だけのメモも until 側の上限で除外されない
+        assert!(matches(mode, None, Some(16), &memo(Some(12), None)));
+        assert!(!matches(mode, None, Some(16), &memo(Some(17), None)));
+        assert!(matches(mode, None, Some(16), &memo(Some(1), Some(16))));
+        assert!(!matches(mode, None, Some(16), &memo(Some(1), Some(17))));
+        // until だけのメモも from 側の下限で除外されない
+        assert!(matches(mode, Some(10), None, &memo(None, Some(12))));
+        assert!(!matches(mode, Some(10), None, &memo(None, Some(9))));
+        assert!(matches(mode, Some(10), None, &memo(Some(10), Some(31))));
+        assert!(!matches(mode, Some(10), None, &memo(Some(9), Some(31))));
+    }
+
+    #[test]
+    fn starts_within_looks_only_at_the_first_day() {
+        let mode = DateMatch::StartsWithin;
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(15), Some(31))));
+        assert!(!matches(mode, Some(10), Some(16), &memo(Some(5), Some(12))));
+        assert!(matches(mode, Some(10), Some(16), &memo(None, Some(14))));
+        assert!(matches(mode, Some(10), None, &memo(Some(20), Some(31))));
+        assert!(!matches(mode, None, Some(16), &memo(Some(17), None)));
+        assert!(matches(mode, None, Some(16), &memo(Some(1), Some(31))));
+    }
+
+    #[test]
+    fn single_day_excludes_multi_day_memos() {
+        let mode = DateMatch::SingleDay;
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(12), None)));
+        assert!(matches(mode, Some(10), Some(16), &memo(Some(12), Some(12))));
+        assert!(matches(mode, Some(10), Some(16), &memo(None, Some(16))));
+        assert!(!matches(
+            mode,
+            Some(10),
+            Some(16),
+            &memo(Some(11), Some(12))
+        ));
+        assert!(!matches(mode, Some(10), Some(16), &memo(Some(9), None)));
+        assert!(matches(mode, None, Some(16), &memo(Some(1), None)));
+        assert!(!matches(mode, Some(10), None, &memo(None, Some(9))));
+    }
+
+    #[test]
+    fn recurring_memos_match_when_the_series_overlaps() {
+        for mode in [
+            DateMatch::Overlaps,
+            DateMatch::Contained,
+            DateMatch::StartsWithin,
+        ] {
+            assert!(matches(mode, Some(10), Some(16), &series(1, None)));
+            assert!(matches(mode, Some(10), Some(16), &series(1, Some(10))));
+            assert!(matches(mode, Some(10), Some(16), &series(16, Some(31))));
+            assert!(!matches(mode, Some(10), Some(16), &series(1, Some(9))));
+            assert!(!matches(mode, Some(10), Some(16), &series(17, None)));
+            assert!(matches(mode, Some(10), None, &series(1, None)));
+            assert!(matches(mode, None, Some(16), &series(16, None)));
+        }
+    }
+
+    #[test]
+    fn span_matches_agrees_with_single_memo_conditions() {
+        let cases = [
+            memo(Some(5), Some(20)),
+            memo(Some(11), Some(12)),
+            memo(Some(12), None),
+            memo(Some(9), Some(10)),
+            memo(Some(16), Some(17)),
+            memo(Some(1), Some(3)),
+        ];
+        let windows = [(Some(10), Some(16)), (Some(10), None), (None, Some(16))];
+        for mode in [
+            DateMatch::Overlaps,
+            DateMatch::Contained,
+            DateMatch::StartsWithin,
+            DateMatch::SingleDay,
+        ] {
+            for dated in &cases {
+                for (start, end) in windows {
+                    let first = dated.from_day.unwrap();
+                    let last = dated.until_day.unwrap_or(first);
+                    let window = window(start, end, Tz::UTC);
+                    assert_eq!(
+                        span_matches(
+                            mode,
+                            window.start_day(),
+                            window.end_day(),
+                            first,
+                            last,
+                            dated.single_day,
+                        ),
+                        matches(mode, start, end, dated),
+                    );
+                }
+            }
+        }
+    }
+
+    /// 時刻付きのメモ (`Asia/Tokyo` の1月 `from` 日〜 `until` 日の `HH:MM`)
+    fn timed(from: (u32, u32, u32), until: Option<(u32, u32, u32)>) -> Dated {
+        let at = |(day, hour, minute): (u32, u32, u32)| MemoDate {
+            date: date(day),
+            time: chrono::NaiveTime::from_hms_opt(hour, minute, 0),
+        };
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        Dated {
+            from_ts: Some(start_timestamp(at(from), tokyo)),
+            until_ts: until.map(|u| end_timestamp(at(u), tokyo)),
+            single_day: until.is_none_or(|u| u.0 == from.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn timed_memos_match_by_their_instant() {
+        let mode = DateMatch::Overlaps;
+        // 16日 23:30 (JST) は UTC でも16日
+        assert!(matches(
+            mode,
+            Some(16),
+            Some(16),
+            &timed((16, 23, 30), None)
+        ));
+        // 17日 8:00 (JST) は UTC では16日 23:00
+        assert!(matches(mode, Some(16), Some(16), &timed((17, 8, 0), None)));
+        assert!(!matches(mode, Some(17), Some(17), &timed((17, 8, 0), None)));
+        // 16日 8:00〜10:00 (JST) は UTC では15日 23:00〜16日 1:00
+        let morning = timed((16, 8, 0), Some((16, 10, 0)));
+        assert!(matches(mode, Some(15), Some(15), &morning));
+        assert!(!matches(DateMatch::Contained, Some(16), Some(16), &morning));
+    }
+
+    #[test]
+    fn date_only_memos_match_by_calendar_date_in_any_timezone() {
+        // サーバーは UTC、リクエストは Asia/Tokyo。メモは UTC の日付境界で保存されている
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        let yesterday = memo(Some(15), None);
+        // 期限切れ (昨日以前に収まる) に入る
+        assert!(matches_in(
+            DateMatch::Contained,
+            &window(None, Some(15), tokyo),
+            &yesterday
+        ));
+        // 今日 (16日) のプリセットには入らない
+        assert!(!matches_in(
+            DateMatch::Overlaps,
+            &window(Some(16), Some(16), tokyo),
+            &yesterday
+        ));
+        assert!(matches_in(
+            DateMatch::Overlaps,
+            &window(Some(15), Some(15), tokyo),
+            &yesterday
+        ));
+
+        let los_angeles: Tz = "America/Los_Angeles".parse().unwrap();
+        assert!(matches_in(
+            DateMatch::SingleDay,
+            &window(Some(15), Some(15), los_angeles),
+            &yesterday
+        ));
+        assert!(!matches_in(
+            DateMatch::StartsWithin,
+            &window(Some(16), None, los_angeles),
+            &yesterday
+        ));
+        assert!(matches_in(
+            DateMatch::Overlaps,
+            &window(Some(14), Some(16), los_angeles),
+            &series(10, Some(15))
+        ));
+        assert!(!matches_in(
+            DateMatch::Overlaps,
+            &window(Some(16), None, tokyo),
+            &series(10, Some(15))
+        ));
+    }
+
+    #[test]
+    fn timed_memos_use_the_window_timezone() {
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        // 17日 8:00 (JST) は東京の区間では17日
+        assert!(matches_in(
+            DateMatch::Overlaps,
+            &window(Some(17), Some(17), tokyo),
+            &timed((17, 8, 0), None)
+        ));
+        assert!(!matches_in(
+            DateMatch::Overlaps,
+            &window(Some(16), Some(16), tokyo),
+            &timed((17, 8, 0), None)
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+use serde::Serialize;
+
+use crate::error::{AppError, Result};
+use crate::models::{Memo, MemoResponse};
+use crate::services::vector_math::dot;
+use crate::services::QdrantService;
+
+/// 作成時に返す重複候補の最大件数
+const MAX_CANDIDATES: u32 = 5;
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateCandidate {
+    #[serde(flatten)]
+    pub memo: MemoResponse,
+    /// コサイン類似度 (0-1)
+    pub similarity: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateCluster {
+    pub memos: Vec<MemoResponse>,
+    /// クラスタ内で最も似ている組の類似度
+    pub max_similarity: f32,
+}
+
+/// ほぼ同じ内容のメモを見つける。しきい値はEmbedderの生のコサイン類似度で指定する
+#[derive(Clone)]
+pub struct DuplicateDetector {
+    qdrant: QdrantService,
+    threshold: f32,
+}
+
+impl DuplicateDetector {
+    pub fn new(qdrant: QdrantService, threshold: f32) -> Self {
+        Self { qdrant, threshold }
+    }
+
+    /// 新しいメモのベクトルに対する重複候補
+    pub async fn candidates(&self, vector: &[f32], demo: bool) -> Result<Vec<DuplicateCandidate>> {
+        let neighbours = self
+            .qdrant
+            .nearest_memos(vector.to_vec(), MAX_CANDIDATES, self.threshold, demo)
+            .await?;
+
+        Ok(neighbours
+            .into_iter()
+            .map(|(memo, similarity)| DuplicateCandidate {
+                memo: memo.into(),
+                similarity,
+            })
+            .collect())
+    }
+
+    /// 既存メモを類似度 `threshold` 以上でつないだ連結成分ごとにまとめる。2件以上のものだけ返す
+    pub async fn clusters(
+        &self,
+        threshold: Option<f32>,
+        demo: bool,
+    ) -> Result<Vec<DuplicateCluster>> {
+        let threshold = threshold.unwrap_or(self.threshold);
+        let memos = self.qdrant.scroll_memo_vectors(demo).await?;
+
+        // 全組の比較は件数の2乗で重いので、非同期ランタイムの外で行う
+        tokio::task::spawn_blocking(move || build_clusters(memos, threshold))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+}
+
+fn build_clusters(memos: Vec<(Memo, Vec<f32>)>, threshold: f32) -> Vec<DuplicateCluster> {
+    // コサイン距離のコレクションではベクトルが正規化済みなので内積 = 類似度
+    let mut sets = DisjointSet::new(memos.len());
+    let mut best = vec![0.0_f32; memos.len()];
+    for i in 0..memos.len() {
+        for j in (i + 1)..memos.len() {
+            let similarity = dot(&memos[i].1, &memos[j].1);
+            if similarity >= threshold {
+                sets.union(i, j);
+                best[i] = best[i].max(similarity);
+                best[j] = best[j].max(similarity);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); memos.len()];
+    for i in 0..memos.len() {
+        groups[sets.find(i)].push(i);
+    }
+
+    let mut memos: Vec<Option<Memo>> = memos.into_iter().map(|(m, _)| Some(m)).collect();
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let max_similarity = group.iter().map(|&i| best[i]).fold(0.0, f32::max);
+            let mut members: Vec<Memo> = group.iter().filter_map(|&i| memos[i].take()).collect();
+            members.sort_by_key(|m| m.date_added);
+            DuplicateCluster {
+                memos: members.into_iter().map(Into::into).collect(),
+                max_similarity,
+            }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| b.max_similarity.total_cmp(&a.max_similarity));
+    clusters
+}
+
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut node = i;
+        while self.parent[node] != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra != rb {
+            self.parent[rb] = ra;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    /// 追加順に date_added をずらしたメモとベクトル
+    fn memos(vectors: &[(&str, [f32; 2])]) -> Vec<(Memo, Vec<f32>)> {
+        let start = Utc::now();
+        vectors
+            .iter()
+            .enumerate()
+            .map(|(i, (content, vector))| {
+                let memo = Memo {
+                    id: Uuid::new_v4(),
+                    content: content.to_string(),
+                    memo_type: crate::models::MemoType::Flash,
+                    from: None,
+                    until: None,
+                    timezone: None,
+                    tags: Vec::new(),
+                    date_added: start + Duration::seconds(i as i64),
+                    access_count: 0,
+                    last_accessed: start,
+                    completed: false,
+                    embedding_status: Default::default(),
+                    ics_uid: None,
+                    recurrence: None,
+                    completed_occurrences: Vec::new(),
+                };
+                (memo, vector.to_vec())
+            })
+            .collect()
+    }
+
+    fn contents(cluster: &DuplicateCluster) -> Vec<&str> {
+        cluster.memos.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    /// 単位ベクトル (cos, sin)
+    fn unit(degrees: f32) -> [f32; 2] {
+        let radians = degrees.to_radians();
+        [radians.cos(), radians.sin()]
+    }
+
+    #[test]
+    fn groups_transitively_connected_memos() {
+        // a-b, b-c は近いが a-c は遠い。それでも同じクラスタにまとめる
+        let clusters = build_clusters(
+            memos(&[
+                ("a", unit(0.0)),
+                ("b", unit(20.0)),
+                ("c", unit(40.0)),
+                ("far", unit(90.0)),
+            ]),
+            20.5_f32.to_radians().cos(),
+        );
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(contents(&clusters[0]), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn similarity_equal_to_threshold_counts_as_duplicate() {
+        let clusters = build_clusters(
+            memos(&[("a", [1.0, 0.0]), ("b", [0.5, 0.0]), ("c", [0.0, 1.0])]),
+            0.5,
+        );
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(contents(&clusters[0]), vec!["a", "b"]);
+        assert_eq!(clusters[0].max_similarity, 0.5);
+
+        let clusters = build_clusters(memos(&[("a", [1.0, 0.0]), ("b", [0.5, 0.0])]), 0.51);
+        assert!(clusters.is_empty());
+    }
+
+    #[test]
+    fn sorts_clusters_by_max_similarity() {
+        let clusters = build_clusters(
+            memos(&[
+                ("x1", [1.0, 0.0]),
+                ("y1", [0.0, 1.0]),
+                ("x2", [0.9, 0.0]),
+                ("y2", [0.0, 1.0]),
+            ]),
+            0.8,
+        );
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(contents(&clusters[0]), vec!["y1", "y2"]);
+        assert_eq!(contents(&clusters[1]), vec!["x1", "x2"]);
+    }
+
+    #[test]
+    fn disjoint_set_finds_common_root() {
+        let mut sets = DisjointSet::new(5);
+        sets.union(0, 1);
+        sets.union(3, 4);
+        sets.union(1, 4);
+        let root = sets.find(0);
+        assert!([1, 3, 4].iter().all(|&i| sets.find(i) == root));
+        assert_ne!(sets.find(2), root);
+    }
+}
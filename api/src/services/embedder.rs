@@ -1,11 +1,36 @@
 use crate::error::{AppError, Result};
-use reqwest::Client;
+use crate::models::EmbeddingModel;
+use crate::services::CircuitBreaker;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct EmbedderClient {
     client: Client,
     base_url: String,
+    max_retries: u32,
+    retry_base_delay: Duration,
+    breaker: CircuitBreaker,
+    passage_prefix: String,
+    query_prefix: String,
+    /// 再インデックスでモデルを切り替え中。張り替えまでは新しいモデルのベクトルを
+    /// 今のコレクションに書いたり検索に使ったりしないよう、停止中として扱う
+    switching_model: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbedderOptions {
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_base_delay: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+    pub passage_prefix: String,
+    pub query_prefix: String,
 }
 
 #[derive(Serialize)]
@@ -18,23 +43,104 @@ struct EmbedResponse {
     vector: Vec<f32>,
 }
 
+enum EmbedAttemptError {
+    Transient(String),
+    Fatal(AppError),
+}
+
 impl EmbedderClient {
-    pub fn new(base_url: String) -> Self {
-        Self {
-            client: Client::new(),
+    pub fn new(base_url: String, options: EmbedderOptions) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout)
+            .build()
+            .map_err(|e| AppError::Embedder(format!("Failed to build client: {}", e)))?;
+
+        Ok(Self {
+            client,
             base_url,
-        }
+            max_retries: options.max_retries,
+            retry_base_delay: options.retry_base_delay,
+            breaker: CircuitBreaker::new(options.breaker_threshold, options.breaker_cooldown),
+            passage_prefix: options.passage_prefix,
+            query_prefix: options.query_prefix,
+            switching_model: Arc::new(AtomicBool::new(false)),
+        })
     }
 
     pub async fn embed_for_storage(&self, text: &str) -> Result<Vec<f32>> {
-        self.embed(&format!("passage: {}", text)).await
+        self.check_model_switch()?;
+        self.embed_for_reindex(text).await
     }
 
     pub async fn embed_for_search(&self, query: &str) -> Result<Vec<f32>> {
-        self.embed(&format!("query: {}", query)).await
+        self.check_model_switch()?;
+        self.embed(&format!("{}{}", self.query_prefix, query)).await
+    }
+
+    /// 再インデックスの書き込み先用。モデルの切り替え中でもベクトル化する
+    pub async fn embed_for_reindex(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&format!("{}{}", self.passage_prefix, text))
+            .await
+    }
+
+    /// モデルの切り替えを始める (true) / 終える (false)
+    pub fn set_switching_model(&self, switching: bool) {
+        self.switching_model.store(switching, Ordering::SeqCst);
+    }
+
+    fn check_model_switch(&self) -> Result<()> {
+        if self.switching_model.load(Ordering::SeqCst) {
+            return Err(AppError::EmbedderUnavailable(
+                "Switching embedding model (re-index in progress)".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_available(&self) -> bool {
+        !self.breaker.is_open()
     }
 
     async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if !self.breaker.allow_request() {
+            return Err(AppError::EmbedderUnavailable(
+                "Circuit breaker is open".into(),
+            ));
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.try_embed(text).await {
+                Ok(vector) => {
+                    self.breaker.record_success();
+                    return Ok(vector);
+                }
+                Err(EmbedAttemptError::Fatal(e)) => {
+                    // 応答は返ってきているのでサービス自体は生きている
+                    self.breaker.record_success();
+                    return Err(e);
+                }
+                Err(EmbedAttemptError::Transient(msg)) if attempt >= self.max_retries => {
+                    self.breaker.record_failure();
+                    return Err(AppError::EmbedderUnavailable(msg));
+                }
+                Err(EmbedAttemptError::Transient(msg)) => {
+                    let delay = self.backoff_delay(attempt);
+                    tracing::warn!(
+                        "Embedder attempt {} failed ({}), retrying in {}ms",
+                        attempt + 1,
+                        msg,
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn try_embed(&self, text: &str) -> std::result::Result<Vec<f32>, EmbedAttemptError> {
         let url = format!("{}/embed", self.base_url);
 
         let response = self
@@ -45,32 +151,60 @@ impl EmbedderClient {
             })
             .send()
             .await
-            .map_err(|e| AppError::Embedder(format!("Failed to connect: {}", e)))?;
+            .map_err(|e| EmbedAttemptError::Transient(format!("Failed to connect: {}", e)))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(AppError::Embedder(format!(
-                "Embedder returned {}: {}",
-                status, error_text
-            )));
+            let message = format!("Embedder returned {}: {}", status, error_text);
+            return Err(if is_retryable_status(status) {
+                EmbedAttemptError::Transient(message)
+            } else {
+                EmbedAttemptError::Fatal(AppError::Embedder(message))
+            });
         }
 
-        let data: EmbedResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::Embedder(format!("Failed to parse response: {}", e)))?;
+        let data: EmbedResponse = response.json().await.map_err(|e| {
+            EmbedAttemptError::Fatal(AppError::Embedder(format!(
+                "Failed to parse response: {}",
+                e
+            )))
+        })?;
 
         Ok(data.vector)
     }
 
-    pub async fn health_check(&self) -> Result<()> {
+    /// 指数バックオフ + full jitter
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let max_delay = self
+            .retry_base_delay
+            .saturating_mul(2u32.saturating_pow(attempt));
+        let jittered = rand::random_range(0..=max_delay.as_millis() as u64);
+        Duration::from_millis(jittered)
+    }
+
+    /// Embedderの `/health` から現在ロードされているモデルを取得する
+    pub async fn model_info(&self) -> Result<EmbeddingModel> {
         let url = format!("{}/health", self.base_url);
-        self.client
-            .get(&url)
-            .send()
+        let response =
+            self.client.get(&url).send().await.map_err(|e| {
+                AppError::EmbedderUnavailable(format!("Health check failed: {}", e))
+            })?;
+
+        if !response.status().is_success() {
+            return Err(AppError::EmbedderUnavailable(format!(
+                "Health check returned {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
             .await
-            .map_err(|e| AppError::Embedder(format!("Health check failed: {}", e)))?;
-        Ok(())
+            .map_err(|e| AppError::Embedder(format!("Failed to parse health response: {}", e)))
     }
 }
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
@@ -0,0 +1,113 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::services::{EmbedderClient, QdrantService};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// Embedder停止以外のエラーで失敗したメモを試す回数。超えたら本文が変わるか再起動するまで諦める
+const MAX_ATTEMPTS: u32 = 3;
+
+/// ベクトル化に失敗したメモの本文と失敗回数
+type Failures = HashMap<Uuid, (String, u32)>;
+
+/// `embedding_status = pending` のメモをベクトル化するワーカー。
+/// 状態はQdrantのpayloadに永続化されているので、再起動後も未処理分を拾い直す
+#[derive(Clone)]
+pub struct EmbeddingQueue {
+    notify: Arc<Notify>,
+}
+
+impl EmbeddingQueue {
+    pub fn start(qdrant: QdrantService, embedder: EmbedderClient) -> Self {
+        let notify = Arc::new(Notify::new());
+        tokio::spawn(run_worker(notify.clone(), qdrant, embedder));
+        Self { notify }
+    }
+
+    /// 保留中のメモが追加されたことをワーカーに知らせる
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+}
+
+async fn run_worker(notify: Arc<Notify>, qdrant: QdrantService, embedder: EmbedderClient) {
+    let mut failures = Failures::new();
+    loop {
+        let wait = match drain_pending(&qdrant, &embedder, &mut failures).await {
+            Ok(()) => POLL_INTERVAL,
+            Err(AppError::EmbedderUnavailable(_)) => RETRY_INTERVAL,
+            Err(e) => {
+                tracing::warn!("Embedding worker failed: {}", e);
+                RETRY_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = notify.notified() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+}
+
+async fn drain_pending(
+    qdrant: &QdrantService,
+    embedder: &EmbedderClient,
+    failures: &mut Failures,
+) -> Result<()> {
+    let mut pending_ids = HashSet::new();
+    for demo in [false, true] {
+        for memo in qdrant.list_pending_memos(demo).await? {
+            pending_ids.insert(memo.id);
+            if let Some((content, attempts)) = failures.get(&memo.id) {
+                if *content == memo.content && *attempts >= MAX_ATTEMPTS {
+                    continue;
+                }
+            }
+
+            let vector = match embedder.embed_for_storage(&memo.content).await {
+                Ok(vector) => vector,
+                Err(e @ AppError::EmbedderUnavailable(_)) => return Err(e),
+                Err(e) => {
+                    let failure = failures
+                        .entry(memo.id)
+                        .or_insert_with(|| (memo.content.clone(), 0));
+                    if failure.0 != memo.content {
+                        *failure = (memo.content.clone(), 0);
+                    }
+                    failure.1 += 1;
+                    tracing::error!(
+                        "Failed to embed pending memo {} (attempt {}/{}): {}",
+                        memo.id,
+                        failure.1,
+                        MAX_ATTEMPTS,
+                        e
+                    );
+                    continue;
+                }
+            };
+            failures.remove(&memo.id);
+
+            // ベクトル化中に本文が編集されていたら古いベクトルで上書きしない
+            let Some(current) = qdrant.get_memo(memo.id, demo).await? else {
+                continue;
+            };
+            if current.content != memo.content {
+                continue;
+            }
+
+            // payload はタグ・完了など他の更新と競合しないよう、埋め込みの状態だけを書き換える
+            qdrant.set_memo_vector(memo.id, vector, demo).await?;
+            tracing::info!("Embedded pending memo {}", memo.id);
+        }
+    }
+
+    // 保留でなくなった (編集・削除された) メモの失敗は忘れる
+    failures.retain(|id, _| pending_ids.contains(id));
+    Ok(())
+}
@@ -0,0 +1,554 @@
+use std::io::BufReader;
+
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use ical::parser::ical::component::IcalEvent;
+use ical::property::Property;
+use ical::IcalParser;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{Memo, MemoDate};
+
+/// 1行の最大オクテット数 (RFC 5545 3.1)
+const MAX_LINE_OCTETS: usize = 75;
+/// エクスポートしたイベントの UID のドメイン部
+pub const UID_DOMAIN: &str = "ultnote";
+
+/// 日付のあるメモをイベントとして iCalendar に書き出す。日付の無いメモは飛ばす。
+/// 日付だけのメモは終日イベント、時刻付きのメモはメモのタイムゾーン (無ければ `timezone`) の `TZID` 付きの日時にする
+pub fn render_calendar(memos: &[Memo], timezone: Tz) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//UltNote//Memo Calendar//JA".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:UltNote".to_string(),
+    ];
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    for memo in memos {
+        let (Some(first), Some(last)) = (memo.from.or(memo.until), memo.until.or(memo.from)) else {
+            continue;
+        };
+        let summary = memo.content.lines().next().unwrap_or_default();
+
+        lines.push("BEGIN:VEVENT".into());
+        lines.push(format!("UID:{}@{}", memo.id, UID_DOMAIN));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.extend(event_dates(first, last, memo.timezone.unwrap_or(timezone)));
+        if let Some(ref recurrence) = memo.recurrence {
+            lines.push(format!("RRULE:{}", recurrence));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(summary)));
+        if memo.content.trim() != summary.trim() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&memo.content)));
+        }
+        if !memo.tags.is_empty() {
+            let categories: Vec<String> = memo.tags.iter().map(|t| escape_text(t)).collect();
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        lines.push("END:VEVENT".into());
+    }
+
+    lines.push("END:VCALENDAR".into());
+
+    let mut calendar = String::new();
+    for line in lines {
+        fold_line(&line, &mut calendar);
+    }
+    calendar
+}
+
+/// DTSTART / DTEND の行。DTEND は含まない終端なので、終日なら翌日、時刻の無い終わりは翌日の0時。
+/// 時刻付きの1時点だけのメモには DTEND を付けない
+fn event_dates(first: MemoDate, last: MemoDate, timezone: Tz) -> Vec<String> {
+    if first.time.is_none() && last.time.is_none() {
+        let end = last.date.max(first.date) + Days::new(1);
+        return vec![
+            format!("DTSTART;VALUE=DATE:{}", first.date.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+        ];
+    }
+
+    let start = first.date.and_time(first.time.unwrap_or(NaiveTime::MIN));
+    let end = match last.time {
+        Some(time) => last.date.and_time(time),
+        None => (last.date + Days::new(1)).and_time(NaiveTime::MIN),
+    };
+    let datetime = |name: &str, value: NaiveDateTime| {
+        format!(
+            "{};TZID={}:{}",
+            name,
+            timezone.name(),
+            value.format("%Y%m%dT%H%M%S")
+        )
+    };
+    let mut lines = vec![datetime("DTSTART", start)];
+    if end > start {
+        lines.push(datetime("DTEND", end));
+    }
+    lines
+}
+
+/// TEXT 値のエスケープ (RFC 5545 3.3.11)
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 75オクテットを超える行を折り返す。続きの行は空白1文字で始める。
+/// マルチバイト文字の途中では切らない
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// 取り込んだ VEVENT。終わりは両端を含む日付 (日時) に直してある
+#[derive(Debug, Clone)]
+pub struct ImportedEvent {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: String,
+    pub start: Option<MemoDate>,
+    pub end: Option<MemoDate>,
+    /// 日時の `TZID`。start / end の時刻はこのタイムゾーン (None なら `parse_calendar` の `timezone`) のもの
+    pub timezone: Option<Tz>,
+    pub categories: Vec<String>,
+    /// RRULE の値 (解釈は取り込み側で行う)
+    pub rrule: Option<String>,
+}
+
+impl ImportedEvent {
+    /// このアプリが書き出したイベントなら元のメモID
+    pub fn exported_memo_id(&self) -> Option<Uuid> {
+        let uid = self.uid.as_deref()?;
+        let (id, domain) = uid.rsplit_once('@')?;
+        (domain == UID_DOMAIN).then(|| Uuid::parse_str(id).ok())?
+    }
+
+    /// メモの本文。書き出し時は DESCRIPTION に本文全体を入れているので、SUMMARY で始まっていればそのまま使う
+    pub fn content(&self) -> String {
+        let summary = self.summary.trim();
+        let description = self.description.trim();
+        if description.is_empty() {
+            summary.to_string()
+        } else if summary.is_empty() || description.starts_with(summary) {
+            description.to_string()
+        } else {
+            format!("{}\n\n{}", summary, description)
+        }
+    }
+}
+
+/// iCalendar を読み、含まれる VEVENT をすべて返す。
+/// 日時の値は DTSTART の `TZID` のタイムゾーンの日時に直す。`TZID` が無ければ `timezone` (`Z` 付きはUTCから直す)
+pub fn parse_calendar(text: &str, timezone: Tz) -> Result<Vec<ImportedEvent>> {
+    let mut events = Vec::new();
+    for calendar in IcalParser::new(BufReader::new(text.as_bytes())) {
+        let calendar =
+            calendar.map_err(|e| AppError::BadRequest(format!("Invalid iCalendar: {}", e)))?;
+        events.extend(calendar.events.iter().map(|e| parse_event(e, timezone)));
+    }
+    Ok(events)
+}
+
+fn parse_event(event: &IcalEvent, timezone: Tz) -> ImportedEvent {
+    let property = |name: &str| {
+        event
+            .properties
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    };
+    let text = |name: &str| {
+        property(name)
+            .and_then(|p| p.value.as_deref())
+            .map(unescape_text)
+            .unwrap_or_default()
+    };
+
+    let event_timezone = ["DTSTART", "DTEND"]
+        .iter()
+        .filter_map(|name| property(name))
+        .find_map(|p| param(p, "TZID").and_then(|tz| tz.parse::<Tz>().ok()));
+    let local = event_timezone.unwrap_or(timezone);
+
+    let start = property("DTSTART")
+        .and_then(|p| parse_date_value(p, local))
+        .map(|(date, _)| date);
+    let end = property("DTEND")
+        .and_then(|p| parse_date_value(p, local))
+        .and_then(|(date, exclusive)| {
+            // 終日イベントの DTEND と 0:00 ちょうどの終了時刻は含まないので、前日の終わりまで
+            Some(if exclusive {
+                date.date.pred_opt()?.into()
+            } else {
+                date
+            })
+        });
+    let end = match (start, end) {
+        (Some(start), Some(end)) if ends_before(end, start) => Some(start),
+        (_, end) => end,
+    };
+
+    let categories = event
+        .properties
+        .iter()
+        .filter(|p| p.name.eq_ignore_ascii_case("CATEGORIES"))
+        .filter_map(|p| p.value.as_deref())
+        .flat_map(split_list)
+        .collect();
+
+    ImportedEvent {
+        uid: property("UID")
+            .and_then(|p| p.value.clone())
+            .filter(|uid| !uid.trim().is_empty()),
+        summary: text("SUMMARY"),
+        description: text("DESCRIPTION"),
+        start,
+        end,
+        timezone: event_timezone,
+        categories,
+        rrule: property("RRULE").and_then(|p| p.value.clone()),
+    }
+}
+
+/// 終わり `end` が始まり `start` より前か。同じ日の終日の終わりはどの時刻よりも後
+fn ends_before(end: MemoDate, start: MemoDate) -> bool {
+    match (end.time, start.time) {
+        (Some(end_time), Some(start_time)) if end.date == start.date => end_time < start_time,
+        _ => end.date < start.date,
+    }
+}
+
+/// DATE / DATE-TIME の値を `timezone` の日付 (日時) に直す。2つ目は「この日時を含まない終端」として扱うべきか
+fn parse_date_value(property: &Property, timezone: Tz) -> Option<(MemoDate, bool)> {
+    let value = property.value.as_deref()?.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Some((date.into(), true));
+    }
+
+    let local = if let Some(utc) = value.strip_suffix('Z') {
+        let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        Utc.from_utc_datetime(&utc)
+            .with_timezone(&timezone)
+            .naive_local()
+    } else {
+        let floating = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        match param(property, "TZID").and_then(|tz| tz.parse::<Tz>().ok()) {
+            Some(source) => source
+                .from_local_datetime(&floating)
+                .earliest()
+                .map(|t: DateTime<Tz>| t.with_timezone(&timezone).naive_local())
+                .unwrap_or(floating),
+            None => floating,
+        }
+    };
+
+    Some((local.into(), local.time() == NaiveTime::MIN))
+}
+
+fn param<'p>(property: &'p Property, name: &str) -> Option<&'p str> {
+    property
+        .params
+        .as_ref()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .and_then(|(_, values)| values.first())
+        .map(String::as_str)
+}
+
+/// TEXT 値のエスケープを戻す
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// カンマ区切りの TEXT リスト (CATEGORIES) を分ける。エスケープされたカンマでは分けない
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => items.push(unescape_text(&std::mem::take(&mut current))),
+            _ => {
+                escaped = c == '\\' && !escaped;
+                current.push(c);
+                continue;
+            }
+        }
+        escaped = false;
+    }
+    items.push(unescape_text(&current));
+    items.retain(|item| !item.trim().is_empty());
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, time: Option<(u32, u32)>) -> MemoDate {
+        MemoDate {
+            date: NaiveDate::from_ymd_opt(y, m, d).unwrap(),
+            time: time.and_then(|(h, min)| NaiveTime::from_hms_opt(h, min, 0)),
+        }
+    }
+
+    /// VEVENT 1件だけのカレンダーを読む
+    fn parse_one(lines: &[&str], timezone: Tz) -> ImportedEvent {
+        let text = [
+            &["BEGIN:VCALENDAR", "VERSION:2.0", "BEGIN:VEVENT"][..],
+            lines,
+            &["END:VEVENT", "END:VCALENDAR"][..],
+        ]
+        .concat()
+        .join("\r\n");
+        let mut events = parse_calendar(&text, timezone).unwrap();
+        assert_eq!(events.len(), 1);
+        events.remove(0)
+    }
+
+    #[test]
+    fn all_day_dtend_is_exclusive() {
+        let event = parse_one(
+            &[
+                "UID:a@example.com",
+                "DTSTART;VALUE=DATE:20260115",
+                "DTEND;VALUE=DATE:20260117",
+            ],
+            Tz::UTC,
+        );
+        assert_eq!(event.start, Some(at(2026, 1, 15, None)));
+        assert_eq!(event.end, Some(at(2026, 1, 16, None)));
+        assert_eq!(event.timezone, None);
+
+        let one_day = parse_one(
+            &["DTSTART;VALUE=DATE:20260115", "DTEND;VALUE=DATE:20260116"],
+            Tz::UTC,
+        );
+        assert_eq!(one_day.end, one_day.start);
+    }
+
+    #[test]
+    fn timed_events_keep_their_tzid() {
+        let event = parse_one(
+            &[
+                "DTSTART;TZID=Asia/Tokyo:20260115T150000",
+                "DTEND;TZID=Asia/Tokyo:20260115T163000",
+            ],
+            Tz::UTC,
+        );
+        assert_eq!(event.start, Some(at(2026, 1, 15, Some((15, 0)))));
+        assert_eq!(event.end, Some(at(2026, 1, 15, Some((16, 30)))));
+        assert_eq!(event.timezone, Some(chrono_tz::Asia::Tokyo));
+    }
+
+    #[test]
+    fn utc_times_are_converted_to_the_default_timezone() {
+        let event = parse_one(
+            &["DTSTART:20260115T060000Z", "DTEND:20260115T070000Z"],
+            chrono_tz::Asia::Tokyo,
+        );
+        assert_eq!(event.start, Some(at(2026, 1, 15, Some((15, 0)))));
+        assert_eq!(event.end, Some(at(2026, 1, 15, Some((16, 0)))));
+        assert_eq!(event.timezone, None);
+    }
+
+    #[test]
+    fn midnight_dtend_ends_the_previous_day() {
+        let event = parse_one(
+            &["DTSTART:20260115T220000", "DTEND:20260116T000000"],
+            Tz::UTC,
+        );
+        assert_eq!(event.start, Some(at(2026, 1, 15, Some((22, 0)))));
+        assert_eq!(event.end, Some(at(2026, 1, 15, None)));
+    }
+
+    #[test]
+    fn dtend_before_dtstart_is_clamped() {
+        let event = parse_one(
+            &["DTSTART:20260115T150000", "DTEND:20260115T140000"],
+            Tz::UTC,
+        );
+        assert_eq!(event.end, event.start);
+    }
+
+    #[test]
+    fn text_and_categories_are_unescaped() {
+        let event = parse_one(
+            &[
+                "UID:1f0e6f9a-3c55-4f7b-9a43-6f0a2b1c9d10@ultnote",
+                "SUMMARY:歯医者\\, 定期検診",
+                "DESCRIPTION:歯医者\\, 定期検診\\n持ち物: 診察券\\; 保険証",
+                "CATEGORIES:健康/歯医者,a\\,b",
+                "CATEGORIES:work",
+            ],
+            Tz::UTC,
+        );
+        assert_eq!(event.content(), "歯医者, 定期検診\n持ち物: 診察券; 保険証");
+        assert_eq!(event.categories, vec!["健康/歯医者", "a,b", "work"]);
+        assert_eq!(
+            event.exported_memo_id(),
+            Uuid::parse_str("1f0e6f9a-3c55-4f7b-9a43-6f0a2b1c9d10").ok()
+        );
+    }
+
+    #[test]
+    fn unescape_text_handles_every_escape() {
+        assert_eq!(unescape_text("a\\nb\\Nc"), "a\nb\nc");
+        assert_eq!(unescape_text("\\,\\;\\\\"), ",;\\");
+        assert_eq!(unescape_text("trailing\\"), "trailing\\");
+    }
+
+    #[test]
+    fn split_list_only_splits_on_unescaped_commas() {
+        assert_eq!(split_list("a,b\\,c"), vec!["a", "b,c"]);
+        // エスケープされたバックスラッシュの後のカンマは区切り
+        assert_eq!(split_list("a\\\\,b"), vec!["a\\", "b"]);
+        // 空の項目は捨てる (前後の空白はタグの正規化で落とす)
+        assert_eq!(split_list(" ,a,,"), vec!["a"]);
+    }
+
+    fn memo(content: &str, from: Option<MemoDate>, until: Option<MemoDate>) -> Memo {
+        Memo {
+            id: Uuid::nil(),
+            content: content.to_string(),
+            memo_type: crate::models::MemoType::Flash,
+            from,
+            until,
+            timezone: None,
+            tags: Vec::new(),
+            date_added: Utc::now(),
+            access_count: 0,
+            last_accessed: Utc::now(),
+            completed: false,
+            embedding_status: Default::default(),
+            ics_uid: None,
+            recurrence: None,
+            completed_occurrences: Vec::new(),
+        }
+    }
+
+    /// 折り返しを戻した VEVENT の行 (DTSTAMP は毎回変わるので除く)
+    fn event_lines(memo: &Memo) -> Vec<String> {
+        let calendar = render_calendar(std::slice::from_ref(memo), Tz::Asia__Tokyo);
+        calendar
+            .replace("\r\n ", "")
+            .split("\r\n")
+            .skip_while(|line| *line != "BEGIN:VEVENT")
+            .skip(1)
+            .take_while(|line| *line != "END:VEVENT")
+            .filter(|line| !line.starts_with("UID:") && !line.starts_with("DTSTAMP:"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn fold_line_keeps_multibyte_characters_whole() {
+        let line = format!("DESCRIPTION:{}", "あ".repeat(40));
+        let mut out = String::new();
+        fold_line(&line, &mut out);
+
+        let physical: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        // 12 + 3 * 21 = 75 オクテットでちょうど折り返す
+        assert_eq!(physical[0], format!("DESCRIPTION:{}", "あ".repeat(21)));
+        assert_eq!(physical[1], format!(" {}", "あ".repeat(19)));
+        for part in &physical {
+            assert!(part.len() <= MAX_LINE_OCTETS, "{} octets", part.len());
+        }
+        assert_eq!(out.replace("\r\n ", ""), format!("{}\r\n", line));
+
+        let mut short = String::new();
+        fold_line("SUMMARY:短い", &mut short);
+        assert_eq!(short, "SUMMARY:短い\r\n");
+    }
+
+    #[test]
+    fn render_escapes_text_values() {
+        let mut memo = memo(
+            "会議; 資料, 予算\\案\r\n2行目",
+            Some(at(2026, 1, 15, None)),
+            None,
+        );
+        memo.tags = vec!["仕事/会議".into(), "a,b".into()];
+
+        let lines = event_lines(&memo);
+        assert!(lines.contains(&"SUMMARY:会議\\; 資料\\, 予算\\\\案".to_string()));
+        assert!(lines.contains(&"DESCRIPTION:会議\\; 資料\\, 予算\\\\案\\n2行目".to_string()));
+        assert!(lines.contains(&"CATEGORIES:仕事/会議,a\\,b".to_string()));
+    }
+
+    #[test]
+    fn render_all_day_dtend_is_the_next_day() {
+        let range = memo(
+            "旅行",
+            Some(at(2026, 1, 15, None)),
+            Some(at(2026, 1, 17, None)),
+        );
+        assert_eq!(
+            event_lines(&range)[..2],
+            ["DTSTART;VALUE=DATE:20260115", "DTEND;VALUE=DATE:20260118"]
+        );
+
+        let single = memo("締め切り", None, Some(at(2026, 1, 31, None)));
+        assert_eq!(
+            event_lines(&single)[..2],
+            ["DTSTART;VALUE=DATE:20260131", "DTEND;VALUE=DATE:20260201"]
+        );
+    }
+
+    #[test]
+    fn render_timed_events_use_tzid() {
+        let until_day = memo(
+            "合宿",
+            Some(at(2026, 1, 15, Some((15, 0)))),
+            Some(at(2026, 1, 16, None)),
+        );
+        assert_eq!(
+            event_lines(&until_day)[..2],
+            [
+                "DTSTART;TZID=Asia/Tokyo:20260115T150000",
+                "DTEND;TZID=Asia/Tokyo:20260117T000000"
+            ]
+        );
+
+        let mut point = memo("電話", Some(at(2026, 1, 15, Some((9, 30)))), None);
+        point.timezone = Some(Tz::America__New_York);
+        let lines = event_lines(&point);
+        assert_eq!(lines[0], "DTSTART;TZID=America/New_York:20260115T093000");
+        assert!(!lines.iter().any(|line| line.starts_with("DTEND")));
+    }
+}
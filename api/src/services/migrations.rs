@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use qdrant_client::qdrant::{value::Kind, CreateCollection, FieldType, PointId, Value, Vectors};
+
+use crate::error::Result;
+use crate::models::{MemoDate, Recurrence};
+use crate::services::calendar::{day_end, day_number, day_start};
+use crate::services::date_match::{ALL_DAY, FROM_DAY, RECURRING, SINGLE_DAY, UNTIL_DAY};
+use crate::services::qdrant::{ICS_UID, LOGICAL_COLLECTIONS};
+use crate::services::{QdrantService, TagNormalizer};
+
+/// コピー中のポイント。`transform` で payload やベクトルを書き換える
+pub struct MigratedPoint {
+    pub id: PointId,
+    pub payload: HashMap<String, Value>,
+    pub vectors: Vectors,
+}
+
+/// 変換で参照する設定
+pub struct MigrationContext {
+    pub tag_normalizer: TagNormalizer,
+    /// 日付だけのメモを解釈するタイムゾーン
+    pub timezone: Tz,
+}
+
+/// スキーマの変更1件分。バージョンは連番で、適用済みのバージョンはコレクションの
+/// メタデータ `schema_version` に記録される
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    /// コレクション作成パラメータ (ベクトル設定など) の変更。指定するとコレクションの作り直しになる
+    pub configure: Option<fn(&mut CreateCollection)>,
+    /// コピー時のポイント変換。指定するとコレクションの作り直しになる
+    pub transform: Option<fn(&mut MigratedPoint, &MigrationContext)>,
+    /// payloadインデックス。既存コレクションにもその場で追加できる
+    pub payload_indexes: &'static [(&'static str, FieldType)],
+}
+
+impl Migration {
+    fn requires_rebuild(&self) -> bool {
+        self.configure.is_some() || self.transform.is_some()
+    }
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    // 実体コレクション `{name}_vN` + エイリアス `{name}` 構成への移行
+    Migration {
+        version: 1,
+        name: "alias_layout",
+        configure: None,
+        transform: None,
+        payload_indexes: &[],
+    },
+    // build_filter / スクロールで使うフィールドのインデックス
+    Migration {
+        version: 2,
+        name: "payload_indexes",
+        configure: None,
+        transform: None,
+        payload_indexes: &[
+            ("tags", FieldType::Keyword),
+            ("type", FieldType::Keyword),
+            ("embedding_status", FieldType::Keyword),
+            ("from_ts", FieldType::Integer),
+            ("until_ts", FieldType::Integer),
+            ("completed", FieldType::Bool),
+        ],
+    },
+    // 既存メモのタグを書き込み時と同じ規則で正規化する
+    Migration {
+        version: 3,
+        name: "normalize_tags",
+        configure: None,
+        transform: Some(normalize_tags),
+        payload_indexes: &[],
+    },
+    // from_ts / until_ts をUTCではなく既定タイムゾーンの日付境界で付け直す。
+    // 日付だけのメモは v8 以降 from_day / until_day で比べるので、ここで使ったタイムゾーンは判定に影響しない
+    Migration {
+        version: 4,
+        name: "localize_date_timestamps",
+        configure: None,
+        transform: Some(localize_date_timestamps),
+        payload_indexes: &[],
+    },
+    // date_match=single_day 用に、1日だけのメモかどうかを持たせる
+    Migration {
+        version: 5,
+        name: "single_day_flag",
+        configure: None,
+        transform: Some(mark_single_day),
+        payload_indexes: &[(SINGLE_DAY, FieldType::Bool)],
+    },
+    // iCalendar 取り込み時の UID での重複判定用
+    Migration {
+        version: 6,
+        name: "ics_uid_index",
+        configure: None,
+        transform: None,
+        payload_indexes: &[(ICS_UID, FieldType::Keyword)],
+    },
+    // 日付フィルタで繰り返しメモとそれ以外を分ける。フラグの無い既存メモは繰り返しでない扱い
+    Migration {
+        version: 7,
+        name: "recurring_index",
+        configure: None,
+        transform: None,
+        payload_indexes: &[(RECURRING, FieldType::Bool)],
+    },
+    // 日付だけのメモを暦日で比べる (検索区間のタイムゾーンと保存時のタイムゾーンがずれても日がずれない)
+    Migration {
+        version: 8,
+        name: "calendar_day_fields",
+        configure: None,
+        transform: Some(add_calendar_days),
+        payload_indexes: &[
+            (ALL_DAY, FieldType::Bool),
+            (FROM_DAY, FieldType::Integer),
+            (UNTIL_DAY, FieldType::Integer),
+        ],
+    },
+];
+
+fn normalize_tags(point: &mut MigratedPoint, context: &MigrationContext) {
+    let Some(values) = point.payload.get("tags").and_then(|v| v.try_list_iter()) else {
+        return;
+    };
+    let tags: Vec<String> = values
+        .filter_map(|v| match &v.kind {
+            Some(Kind::StringValue(s)) => Some(s.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let normalized = context.tag_normalizer.normalize_existing(&tags);
+    point.payload.insert("tags".into(), normalized.into());
+}
+
+fn localize_date_timestamps(point: &mut MigratedPoint, context: &MigrationContext) {
+    for (field, ts_field, to_timestamp) in [
+        ("from", "from_ts", day_start as fn(NaiveDate, Tz) -> i64),
+        ("until", "until_ts", day_end),
+    ] {
+        let date = match point.payload.get(field).map(|v| &v.kind) {
+            Some(Some(Kind::StringValue(s))) => NaiveDate::parse_from_str(s, "%Y-%m-%d").ok(),
+            _ => None,
+        };
+        if let Some(date) = date {
+            point
+                .payload
+                .insert(ts_field.into(), to_timestamp(date, context.timezone).into());
+        }
+    }
+}
+
+fn mark_single_day(point: &mut MigratedPoint, _context: &MigrationContext) {
+    let date = |field: &str| match point.payload.get(field).map(|v| &v.kind) {
+        Some(Some(Kind::StringValue(s))) => Some(s.clone()),
+        _ => None,
+    };
+    let single_day = match (date("from"), date("until")) {
+        (None, None) => return,
+        (Some(from), Some(until)) => from == until,
+        _ => true,
+    };
+    point.payload.insert(SINGLE_DAY.into(), single_day.into());
+}
+
+fn add_calendar_days(point: &mut MigratedPoint, _context: &MigrationContext) {
+    let field = |name: &str| match point.payload.get(name).map(|v| &v.kind) {
+        Some(Some(Kind::StringValue(s))) => Some(s.clone()),
+        _ => None,
+    };
+    let date = |name: &str| field(name).and_then(|s| s.parse::<MemoDate>().ok());
+    let (from, until) = (date("from"), date("until"));
+    let recurrence = field("recurrence").and_then(|s| s.parse::<Recurrence>().ok());
+    if from.is_none() && until.is_none() {
+        return;
+    }
+    if from.iter().chain(&until).any(|d| d.time.is_some()) {
+        return;
+    }
+
+    point.payload.insert(ALL_DAY.into(), true.into());
+    if let Some(from) = from {
+        point
+            .payload
+            .insert(FROM_DAY.into(), day_number(from.date).into());
+    }
+
+    let until_day = match (recurrence, from) {
+        (Some(recurrence), Some(from)) => {
+            recurrence.series_end(from.date, Some(until.unwrap_or(from).date))
+        }
+        _ => until.map(|until| until.date),
+    };
+    if let Some(until_day) = until_day {
+        point
+            .payload
+            .insert(UNTIL_DAY.into(), day_number(until_day).into());
+    }
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// 全マイグレーションを適用した最新スキーマで作成パラメータを組み立てる
+pub fn configure_collection(request: &mut CreateCollection) {
+    for configure in MIGRATIONS.iter().filter_map(|m| m.configure) {
+        configure(request);
+    }
+}
+
+pub fn payload_indexes() -> impl Iterator<Item = &'static (&'static str, FieldType)> {
+    MIGRATIONS.iter().flat_map(|m| m.payload_indexes.iter())
+}
+
+/// 未適用のマイグレーションを論理コレクションごとに適用する。
+/// 作り直しが必要な場合は `{name}_v{latest}` を作ってポイントを変換しながらコピーし、
+/// エイリアスを張り替える。起動時、リクエストを受け付ける前に呼ぶこと。
+/// いずれかのコレクションに適用した場合は true
+pub async fn run_migrations(qdrant: &QdrantService, context: &MigrationContext) -> Result<bool> {
+    let latest = latest_version();
+    let mut migrated = false;
+
+    for logical in LOGICAL_COLLECTIONS {
+        let current = qdrant.schema_version(logical).await?;
+        if current >= latest {
+            continue;
+        }
+
+        let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+        for migration in &pending {
+            tracing::info!(
+                "Applying migration {} ({}) to {}",
+                migration.version,
+                migration.name,
+                logical
+            );
+        }
+
+        let needs_rebuild =
+            !qdrant.is_alias(logical).await? || pending.iter().any(|m| m.requires_rebuild());
+
+        if needs_rebuild {
+            let transforms: Vec<fn(&mut MigratedPoint, &MigrationContext)> =
+                pending.iter().filter_map(|m| m.transform).collect();
+            qdrant
+                .rebuild_collection(logical, latest, |point| {
+                    for transform in &transforms {
+                        transform(point, context);
+                    }
+                })
+                .await?;
+        } else {
+            let indexes: Vec<(&str, FieldType)> = pending
+                .iter()
+                .flat_map(|m| m.payload_indexes.iter().copied())
+                .collect();
+            qdrant.create_payload_indexes(logical, &indexes).await?;
+            qdrant.set_schema_version(logical, latest).await?;
+        }
+
+        tracing::info!("{} is at schema version {}", logical, latest);
+        migrated = true;
+    }
+
+    Ok(migrated)
+}
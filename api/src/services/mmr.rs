@@ -0,0 +1,137 @@
+use crate::models::SearchResult;
+use crate::services::vector_math::{dot, normalize};
+
+/// Maximal Marginal Relevance で並べ替える。
+/// 各ステップで `(1 - diversity) * クエリとの類似度 - diversity * 選択済みとの最大類似度` が最大の候補を選ぶ
+pub fn mmr_rerank(
+    query: &[f32],
+    candidates: Vec<(SearchResult, Vec<f32>)>,
+    diversity: f32,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let lambda = 1.0 - diversity.clamp(0.0, 1.0);
+    let query = normalize(query.to_vec());
+    let (results, vectors): (Vec<SearchResult>, Vec<Vec<f32>>) = candidates
+        .into_iter()
+        .map(|(result, vector)| (result, normalize(vector)))
+        .unzip();
+
+    let relevance: Vec<f32> = vectors.iter().map(|v| dot(&query, v)).collect();
+    let mut max_redundancy = vec![f32::NEG_INFINITY; vectors.len()];
+    let mut remaining: Vec<usize> = (0..vectors.len()).collect();
+    let mut selected: Vec<usize> = Vec::with_capacity(limit.min(vectors.len()));
+
+    while selected.len() < limit && !remaining.is_empty() {
+        let (position, &best) = remaining
+            .iter()
+            .enumerate()
+            .max_by(|(_, &a), (_, &b)| {
+                marginal(lambda, relevance[a], max_redundancy[a])
+                    .total_cmp(&marginal(lambda, relevance[b], max_redundancy[b]))
+                    // diversity = 1 でも最初の1件は関連度で選ぶ
+                    .then(relevance[a].total_cmp(&relevance[b]))
+            })
+            .expect("remaining is not empty");
+
+        remaining.swap_remove(position);
+        selected.push(best);
+
+        for &i in &remaining {
+            max_redundancy[i] = max_redundancy[i].max(dot(&vectors[i], &vectors[best]));
+        }
+    }
+
+    let mut results: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+    selected
+        .into_iter()
+        .filter_map(|i| results[i].take())
+        .collect()
+}
+
+fn marginal(lambda: f32, relevance: f32, redundancy: f32) -> f32 {
+    // 最初の1件は選択済みが無いので関連度だけで選ぶ
+    let redundancy = if redundancy.is_finite() {
+        redundancy
+    } else {
+        0.0
+    };
+    lambda * relevance - (1.0 - lambda) * redundancy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    /// クエリ (1, 0) から `degrees` 度ずれた単位ベクトルの候補
+    fn candidate(content: &str, degrees: f32) -> (SearchResult, Vec<f32>) {
+        let radians = degrees.to_radians();
+        let result = SearchResult {
+            id: Uuid::new_v4(),
+            content: content.to_string(),
+            score: radians.cos(),
+            tags: Vec::new(),
+            from: None,
+            date_added: Utc::now(),
+            completed: false,
+        };
+        (result, vec![radians.cos(), radians.sin()])
+    }
+
+    fn rerank(
+        candidates: Vec<(SearchResult, Vec<f32>)>,
+        diversity: f32,
+        limit: usize,
+    ) -> Vec<String> {
+        mmr_rerank(&[1.0, 0.0], candidates, diversity, limit)
+            .into_iter()
+            .map(|r| r.content)
+            .collect()
+    }
+
+    #[test]
+    fn zero_diversity_keeps_relevance_order() {
+        let candidates = vec![
+            candidate("c", 30.0),
+            candidate("a", 5.0),
+            candidate("d", 80.0),
+            candidate("b", 6.0),
+        ];
+        assert_eq!(rerank(candidates, 0.0, 10), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn full_diversity_starts_with_the_most_relevant() {
+        // 2件目からは選択済みと最も似ていないもの
+        let candidates = vec![
+            candidate("a", 0.0),
+            candidate("b", 10.0),
+            candidate("c", 90.0),
+            candidate("d", 45.0),
+        ];
+        assert_eq!(rerank(candidates, 1.0, 10), vec!["a", "c", "d", "b"]);
+    }
+
+    #[test]
+    fn near_duplicates_are_pushed_down() {
+        let candidates = || {
+            vec![
+                candidate("a", 8.0),
+                candidate("a-copy", 10.0),
+                candidate("other", 60.0),
+            ]
+        };
+        assert_eq!(rerank(candidates(), 0.0, 3), vec!["a", "a-copy", "other"]);
+        assert_eq!(rerank(candidates(), 0.7, 3), vec!["a", "other", "a-copy"]);
+    }
+
+    #[test]
+    fn respects_limit_and_normalizes_vectors() {
+        let mut candidates = vec![candidate("a", 0.0), candidate("b", 20.0)];
+        // 長さが違っても向きで比べる
+        candidates[1].1 = candidates[1].1.iter().map(|x| x * 10.0).collect();
+        assert_eq!(rerank(candidates, 0.0, 1), vec!["a"]);
+        assert!(rerank(Vec::new(), 0.5, 3).is_empty());
+    }
+}
@@ -1,5 +1,38 @@
+mod calendar;
+mod circuit_breaker;
+mod clustering;
+mod date_match;
+mod duplicates;
 mod embedder;
+mod embedding_queue;
+mod ics;
+mod migrations;
+mod mmr;
 mod qdrant;
+mod recurrence;
+mod reindex;
+mod reminder_channels;
+mod reminders;
+mod search_query;
+mod tag_match;
+mod tag_normalizer;
+mod tag_registry;
+mod vector_math;
 
-pub use embedder::EmbedderClient;
+pub use calendar::{start_timestamp, Calendar};
+pub use circuit_breaker::CircuitBreaker;
+pub use clustering::{ClusterReport, ClusteringJob};
+pub use duplicates::{DuplicateCandidate, DuplicateCluster, DuplicateDetector};
+pub use embedder::{EmbedderClient, EmbedderOptions};
+pub use embedding_queue::EmbeddingQueue;
+pub use ics::{parse_calendar, render_calendar, ImportedEvent};
+pub use migrations::{run_migrations, MigrationContext};
+pub use mmr::mmr_rerank;
 pub use qdrant::QdrantService;
+pub use recurrence::occurrence_span;
+pub use reindex::{ReindexJob, ReindexStatus};
+pub use reminder_channels::{EmailChannel, ReminderChannel, SmtpOptions, SmtpSecurity};
+pub use reminders::ReminderScheduler;
+pub use search_query::parse_query;
+pub use tag_normalizer::TagNormalizer;
+pub use tag_registry::{TagEntry, TagRegistry, TagRewrite, TagSuggestion};
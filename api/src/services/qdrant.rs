@@ -1,62 +1,424 @@
 use crate::error::{AppError, Result};
-use crate::models::{Memo, MemoType, SearchFilters, SearchResult};
+use crate::models::{
+    EmbeddingModel, EmbeddingStatus, Memo, MemoDate, MemoType, Recurrence, SearchFilters,
+    SearchResult,
+};
+use crate::services::calendar::{day_number, end_timestamp, start_timestamp};
+use crate::services::date_match::{
+    date_conditions, ALL_DAY, FROM_DAY, RECURRING, SINGLE_DAY, UNTIL_DAY,
+};
+use crate::services::migrations::{self, MigratedPoint};
+use crate::services::Calendar;
 use chrono::{NaiveDate, Utc};
 use qdrant_client::qdrant::{
-    point_id::PointIdOptions, Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance,
-    Filter, GetPointsBuilder, PointId, PointStruct, PointsIdsList, SearchPointsBuilder,
-    UpsertPointsBuilder, Value, VectorParamsBuilder,
+    point_id::PointIdOptions, vector_output, vectors_output::VectorsOptions, Condition,
+    CreateAliasBuilder, CreateCollectionBuilder, CreateFieldIndexCollectionBuilder,
+    DeletePointsBuilder, Distance, FieldType, Filter, GetPointsBuilder, PointId, PointStruct,
+    PointVectors, PointsIdsList, ScrollPointsBuilder, SearchPointsBuilder, SetPayloadPointsBuilder,
+    UpdateCollectionBuilder, UpdatePointVectorsBuilder, UpsertPointsBuilder, Value, Vector,
+    VectorOutput, VectorParamsBuilder, Vectors, VectorsOutput,
 };
 use qdrant_client::Qdrant;
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::OwnedRwLockWriteGuard;
 use uuid::Uuid;
 
-const VECTOR_SIZE: u64 = 768;
 const COLLECTION_MEMOS: &str = "memos";
 const COLLECTION_MEMOS_DEMO: &str = "memos_demo";
 const COLLECTION_MEMOS_ARCHIVE: &str = "memos_archive";
+const EMBEDDING_STATUS_PENDING: &str = "pending";
+pub(crate) const ICS_UID: &str = "ics_uid";
+const RECURRENCE: &str = "recurrence";
+const COMPLETED_OCCURRENCES: &str = "completed_occurrences";
+const TIMEZONE: &str = "timezone";
+/// エイリアス導入前のコレクションを置き換える張り替え先に、論理名を記録するメタデータ
+const LEGACY_ALIAS: &str = "legacy_alias";
+
+/// 論理コレクション名。実体は `{name}_v{schema_version}` で、論理名はエイリアスとして張る
+pub const LOGICAL_COLLECTIONS: [&str; 3] = [
+    COLLECTION_MEMOS,
+    COLLECTION_MEMOS_DEMO,
+    COLLECTION_MEMOS_ARCHIVE,
+];
 
 #[derive(Clone)]
 pub struct QdrantService {
     client: Qdrant,
+    model: Arc<RwLock<EmbeddingModel>>,
+    calendar: Calendar,
+    /// メモの書き込みは共有、再インデックスの最後の差分反映と張り替えは排他で取る
+    writes: Arc<tokio::sync::RwLock<()>>,
 }
 
 impl QdrantService {
-    pub async fn new(url: &str) -> Result<Self> {
+    pub async fn new(url: &str, model: EmbeddingModel, calendar: Calendar) -> Result<Self> {
         let client = Qdrant::from_url(url)
             .build()
             .map_err(|e| AppError::Qdrant(e.to_string()))?;
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            model: Arc::new(RwLock::new(model)),
+            calendar,
+            writes: Arc::new(tokio::sync::RwLock::new(())),
+        })
+    }
+
+    /// 返したガードを持っている間、論理コレクションへのメモの書き込みを待たせる
+    pub(crate) async fn pause_writes(&self) -> OwnedRwLockWriteGuard<()> {
+        self.writes.clone().write_owned().await
+    }
+
+    pub(crate) fn client(&self) -> &Qdrant {
+        &self.client
+    }
+
+    /// 新規に書き込むベクトルの生成元モデル
+    pub fn current_model(&self) -> EmbeddingModel {
+        self.model.read().unwrap().clone()
+    }
+
+    pub fn set_current_model(&self, model: EmbeddingModel) {
+        *self.model.write().unwrap() = model;
     }
 
     pub async fn ensure_collections(&self) -> Result<()> {
-        for collection in [COLLECTION_MEMOS, COLLECTION_MEMOS_DEMO, COLLECTION_MEMOS_ARCHIVE] {
+        for collection in LOGICAL_COLLECTIONS {
             self.ensure_collection(collection).await?;
         }
         Ok(())
     }
 
     async fn ensure_collection(&self, name: &str) -> Result<()> {
+        if self.resolve_alias(name).await?.is_some() {
+            return Ok(());
+        }
+
         let exists = self
             .client
             .collection_exists(name)
             .await
             .map_err(|e| AppError::Qdrant(e.to_string()))?;
 
-        if !exists {
+        if exists {
+            // エイリアス導入前のコレクション。モデル情報が無ければ現在のモデルで記録する
+            if self.stored_model(name).await?.is_none() {
+                let model = self.current_model();
+                self.client
+                    .update_collection(
+                        UpdateCollectionBuilder::new(name).metadata(model_metadata(&model)),
+                    )
+                    .await
+                    .map_err(|e| AppError::Qdrant(e.to_string()))?;
+            }
+            return Ok(());
+        }
+
+        if let Some(target) = self.interrupted_legacy_swap(name).await? {
+            tracing::warn!("Resuming interrupted alias swap {} -> {}", name, target);
+            return self.swap_alias(name, &target).await;
+        }
+
+        let physical = format!("{}_v{}", name, migrations::latest_version());
+        self.create_physical_collection(&physical, &self.current_model())
+            .await?;
+        self.swap_alias(name, &physical).await?;
+
+        Ok(())
+    }
+
+    /// 旧コレクションを削除した後、エイリアスを張る前に中断した張り替え先
+    async fn interrupted_legacy_swap(&self, alias: &str) -> Result<Option<String>> {
+        let collections = self
+            .client
+            .list_collections()
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        let prefix = format!("{}_v", alias);
+        for collection in collections.collections {
+            if !collection.name.starts_with(&prefix) {
+                continue;
+            }
+            let metadata = self.collection_metadata(&collection.name).await?;
+            if self
+                .get_string_field(&metadata, LEGACY_ALIAS)
+                .ok()
+                .as_deref()
+                == Some(alias)
+            {
+                return Ok(Some(collection.name));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 最新スキーマ (全マイグレーション適用済み) で実体コレクションを作成する
+    pub(crate) async fn create_physical_collection(
+        &self,
+        name: &str,
+        model: &EmbeddingModel,
+    ) -> Result<()> {
+        let mut metadata = model_metadata(model);
+        metadata.insert(
+            "schema_version".to_string(),
+            migrations::latest_version().into(),
+        );
+
+        let mut request = CreateCollectionBuilder::new(name)
+            .vectors_config(VectorParamsBuilder::new(model.dimension, Distance::Cosine))
+            .metadata(metadata)
+            .build();
+        migrations::configure_collection(&mut request);
+
+        self.client
+            .create_collection(request)
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        let indexes: Vec<(&str, FieldType)> = migrations::payload_indexes().copied().collect();
+        self.create_payload_indexes(name, &indexes).await?;
+
+        tracing::info!("Created collection: {} ({})", name, model.id);
+        Ok(())
+    }
+
+    /// payloadインデックスを作成する。既に同じインデックスがあれば何もしない
+    pub(crate) async fn create_payload_indexes(
+        &self,
+        collection: &str,
+        indexes: &[(&str, FieldType)],
+    ) -> Result<()> {
+        for (field, field_type) in indexes {
             self.client
-                .create_collection(
-                    CreateCollectionBuilder::new(name)
-                        .vectors_config(VectorParamsBuilder::new(VECTOR_SIZE, Distance::Cosine)),
+                .create_field_index(
+                    CreateFieldIndexCollectionBuilder::new(collection, *field, *field_type)
+                        .wait(true),
                 )
                 .await
                 .map_err(|e| AppError::Qdrant(e.to_string()))?;
-            tracing::info!("Created collection: {}", name);
         }
+        Ok(())
+    }
+
+    pub(crate) async fn schema_version(&self, collection: &str) -> Result<u32> {
+        let metadata = self.collection_metadata(collection).await?;
+        Ok(self
+            .get_int_field(&metadata, "schema_version")
+            .map(|v| v as u32)
+            .unwrap_or(0))
+    }
+
+    pub(crate) async fn set_schema_version(&self, collection: &str, version: u32) -> Result<()> {
+        let target = self
+            .resolve_alias(collection)
+            .await?
+            .unwrap_or_else(|| collection.to_string());
+        let metadata = HashMap::from([("schema_version".to_string(), version.into())]);
+
+        self.client
+            .update_collection(UpdateCollectionBuilder::new(target).metadata(metadata))
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        Ok(())
+    }
+
+    /// `logical` の全ポイントを `{logical}_v{version}` に変換しながらコピーし、エイリアスを張り替える。
+    /// ベクトルはそのまま引き継ぐ
+    pub(crate) async fn rebuild_collection(
+        &self,
+        logical: &str,
+        version: u32,
+        transform: impl Fn(&mut MigratedPoint),
+    ) -> Result<()> {
+        let model = match self.stored_model(logical).await? {
+            Some(model) => model,
+            None => self.current_model(),
+        };
+
+        let target = format!("{}_v{}", logical, version);
+        if self.resolve_alias(logical).await?.as_deref() == Some(target.as_str()) {
+            return Ok(());
+        }
+        let leftover = self
+            .client
+            .collection_exists(&target)
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        if leftover {
+            // 前回途中で失敗したマイグレーションの残骸
+            self.drop_collection(&target).await?;
+        }
+
+        self.create_physical_collection(&target, &model).await?;
+        if let Err(e) = self.copy_points(logical, &target, transform).await {
+            self.drop_collection(&target).await?;
+            return Err(e);
+        }
+
+        self.swap_alias(logical, &target).await
+    }
+
+    async fn copy_points(
+        &self,
+        source: &str,
+        target: &str,
+        transform: impl Fn(&mut MigratedPoint),
+    ) -> Result<()> {
+        let mut offset: Option<PointId> = None;
+        let page_size = 100u32;
+        let mut copied = 0usize;
+
+        loop {
+            let mut scroll_builder = ScrollPointsBuilder::new(source)
+                .with_payload(true)
+                .with_vectors(true)
+                .limit(page_size);
+
+            if let Some(ref off) = offset {
+                scroll_builder = scroll_builder.offset(off.clone());
+            }
+
+            let result = self
+                .client
+                .scroll(scroll_builder)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+            let mut points = Vec::with_capacity(result.result.len());
+            for point in result.result {
+                let Some(id) = point.id.clone() else {
+                    continue;
+                };
+                let mut migrated = MigratedPoint {
+                    id,
+                    vectors: vectors_for_upsert(point.vectors),
+                    payload: point.payload,
+                };
+                transform(&mut migrated);
+                points.push(PointStruct {
+                    id: Some(migrated.id),
+                    payload: migrated.payload,
+                    vectors: Some(migrated.vectors),
+                });
+            }
+
+            if !points.is_empty() {
+                copied += points.len();
+                self.client
+                    .upsert_points(UpsertPointsBuilder::new(target, points).wait(true))
+                    .await
+                    .map_err(|e| AppError::Qdrant(e.to_string()))?;
+            }
+
+            match result.next_page_offset {
+                Some(next_offset) => offset = Some(next_offset),
+                None => break,
+            }
+        }
+
+        tracing::info!("Copied {} points from {} to {}", copied, source, target);
+        Ok(())
+    }
 
+    pub(crate) async fn drop_collection(&self, name: &str) -> Result<()> {
+        self.client
+            .delete_collection(name)
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        tracing::info!("Dropped collection: {}", name);
         Ok(())
     }
 
+    pub(crate) async fn is_alias(&self, name: &str) -> Result<bool> {
+        Ok(self.resolve_alias(name).await?.is_some())
+    }
+
+    /// エイリアスが指す実体コレクション名
+    pub(crate) async fn resolve_alias(&self, alias: &str) -> Result<Option<String>> {
+        let aliases = self
+            .client
+            .list_aliases()
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        Ok(aliases
+            .aliases
+            .into_iter()
+            .find(|a| a.alias_name == alias)
+            .map(|a| a.collection_name))
+    }
+
+    /// エイリアス `alias` を `target` に張り替え、以前の実体コレクションを削除する
+    pub(crate) async fn swap_alias(&self, alias: &str, target: &str) -> Result<()> {
+        let previous = self.resolve_alias(alias).await?;
+
+        if previous.is_none()
+            && self
+                .client
+                .collection_exists(alias)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?
+        {
+            // エイリアス導入前の実体コレクションは同名のエイリアスと共存できないため、
+            // この初回だけは削除 → エイリアス作成の2ステップになる。
+            // 間で落ちても次回起動時に張り直せるよう、先に張り替え先へ記録しておく
+            tracing::warn!("Replacing legacy collection {} with an alias", alias);
+            let metadata = HashMap::from([(LEGACY_ALIAS.to_string(), alias.into())]);
+            self.client
+                .update_collection(UpdateCollectionBuilder::new(target).metadata(metadata))
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+            self.drop_collection(alias).await?;
+        }
+
+        // 既存エイリアスへの create_alias は1操作で張り替わる (アトミック)
+        self.client
+            .create_alias(CreateAliasBuilder::new(target, alias))
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        tracing::info!("Alias {} -> {}", alias, target);
+
+        if let Some(previous) = previous.filter(|p| p != target) {
+            self.drop_collection(&previous).await?;
+        }
+
+        Ok(())
+    }
+
+    /// `memos` に格納されているベクトルのモデル
+    pub async fn active_model(&self) -> Result<Option<EmbeddingModel>> {
+        self.stored_model(COLLECTION_MEMOS).await
+    }
+
+    /// コレクションのメタデータに記録されたモデル
+    pub async fn stored_model(&self, collection: &str) -> Result<Option<EmbeddingModel>> {
+        let metadata = self.collection_metadata(collection).await?;
+
+        let id = self.get_string_field(&metadata, "embedding_model").ok();
+        let dimension = self.get_int_field(&metadata, "embedding_dim").ok();
+
+        Ok(id.zip(dimension).map(|(id, dimension)| EmbeddingModel {
+            id,
+            dimension: dimension as u64,
+        }))
+    }
+
+    async fn collection_metadata(&self, collection: &str) -> Result<HashMap<String, Value>> {
+        let info = self
+            .client
+            .collection_info(collection)
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        Ok(info
+            .result
+            .and_then(|r| r.config)
+            .map(|c| c.metadata)
+            .unwrap_or_default())
+    }
+
     pub async fn insert_memo(&self, memo: &Memo, vector: Vec<f32>, demo: bool) -> Result<()> {
         let collection = if demo {
             COLLECTION_MEMOS_DEMO
@@ -64,8 +426,19 @@ impl QdrantService {
             COLLECTION_MEMOS
         };
 
-        let payload = self.memo_to_payload(memo);
+        let _writes = self.writes.read().await;
+        self.upsert_memo_into(collection, memo, vector, &self.current_model())
+            .await
+    }
 
+    pub(crate) async fn upsert_memo_into(
+        &self,
+        collection: &str,
+        memo: &Memo,
+        vector: Vec<f32>,
+        model: &EmbeddingModel,
+    ) -> Result<()> {
+        let payload = self.memo_point_payload(memo, model);
         let point = PointStruct::new(memo.id.to_string(), vector, payload);
 
         self.client
@@ -76,6 +449,209 @@ impl QdrantService {
         Ok(())
     }
 
+    /// ベクトルはそのままで payload だけを置き換える
+    pub(crate) async fn overwrite_memo_payload_in(
+        &self,
+        collection: &str,
+        memo: &Memo,
+        model: &EmbeddingModel,
+    ) -> Result<()> {
+        let payload = self.memo_point_payload(memo, model);
+        let filter = Filter::must([Condition::has_id([memo.id.to_string()])]);
+
+        self.client
+            .overwrite_payload(
+                SetPayloadPointsBuilder::new(collection, payload)
+                    .points_selector(filter)
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_memos_in(&self, collection: &str, ids: &[Uuid]) -> Result<()> {
+        let ids: Vec<PointId> = ids.iter().map(|id| id.to_string().into()).collect();
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(collection)
+                    .points(PointsIdsList { ids })
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn memo_point_payload(&self, memo: &Memo, model: &EmbeddingModel) -> HashMap<String, Value> {
+        let mut payload = self.memo_to_payload(memo);
+        payload.insert("embedding_model".into(), model.id.clone().into());
+        payload.insert("embedding_dim".into(), (model.dimension as i64).into());
+        payload
+    }
+
+    /// ベクトル未確定のメモをゼロベクトルで保存する。ベクトル検索からは除外される
+    pub async fn insert_pending_memo(&self, memo: &Memo, demo: bool) -> Result<()> {
+        let dimension = self.current_model().dimension as usize;
+        self.insert_memo(memo, vec![0.0; dimension], demo).await
+    }
+
+    /// 保留中だったメモにベクトルを入れる。payload は埋め込みの状態とモデルだけを書き換える
+    pub async fn set_memo_vector(&self, id: Uuid, vector: Vec<f32>, demo: bool) -> Result<()> {
+        let collection = if demo {
+            COLLECTION_MEMOS_DEMO
+        } else {
+            COLLECTION_MEMOS
+        };
+        let model = self.current_model();
+
+        let _writes = self.writes.read().await;
+        self.client
+            .update_vectors(
+                UpdatePointVectorsBuilder::new(
+                    collection,
+                    vec![PointVectors {
+                        id: Some(id.to_string().into()),
+                        vectors: Some(vector.into()),
+                    }],
+                )
+                .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("embedding_status".into(), "ready".into());
+        payload.insert("embedding_model".into(), model.id.into());
+        payload.insert("embedding_dim".into(), (model.dimension as i64).into());
+        self.client
+            .set_payload(
+                SetPayloadPointsBuilder::new(collection, payload)
+                    .points_selector(Filter::must([Condition::has_id([id.to_string()])]))
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn list_pending_memos(&self, demo: bool) -> Result<Vec<Memo>> {
+        let filter = Filter::must([Condition::matches(
+            "embedding_status",
+            EMBEDDING_STATUS_PENDING.to_string(),
+        )]);
+        self.scroll_memos(Some(filter), demo).await
+    }
+
+    /// フィルタに一致するメモを全件取得する
+    pub async fn scroll_memos(&self, filter: Option<Filter>, demo: bool) -> Result<Vec<Memo>> {
+        let collection = if demo {
+            COLLECTION_MEMOS_DEMO
+        } else {
+            COLLECTION_MEMOS
+        };
+
+        self.scroll_memos_in(collection, filter).await
+    }
+
+    /// `memos_archive` のメモを全件取得する
+    pub async fn scroll_archived_memos(&self, filter: Option<Filter>) -> Result<Vec<Memo>> {
+        self.scroll_memos_in(COLLECTION_MEMOS_ARCHIVE, filter).await
+    }
+
+    pub(crate) async fn scroll_memos_in(
+        &self,
+        collection: &str,
+        filter: Option<Filter>,
+    ) -> Result<Vec<Memo>> {
+        let mut memos = Vec::new();
+        let mut offset: Option<PointId> = None;
+        let page_size = 100u32;
+
+        loop {
+            let mut scroll_builder = ScrollPointsBuilder::new(collection)
+                .with_payload(true)
+                .limit(page_size);
+
+            if let Some(ref f) = filter {
+                scroll_builder = scroll_builder.filter(f.clone());
+            }
+            if let Some(ref off) = offset {
+                scroll_builder = scroll_builder.offset(off.clone());
+            }
+
+            let result = self
+                .client
+                .scroll(scroll_builder)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+            for point in &result.result {
+                let id = self.extract_uuid_from_point_id(&point.id)?;
+                memos.push(self.payload_to_memo(id, &point.payload)?);
+            }
+
+            match result.next_page_offset {
+                Some(next_offset) => offset = Some(next_offset),
+                None => break,
+            }
+        }
+
+        Ok(memos)
+    }
+
+    /// ベクトル化済みのメモをベクトル付きで全件取得する
+    pub async fn scroll_memo_vectors(&self, demo: bool) -> Result<Vec<(Memo, Vec<f32>)>> {
+        let collection = if demo {
+            COLLECTION_MEMOS_DEMO
+        } else {
+            COLLECTION_MEMOS
+        };
+        let filter = Filter::must_not([Condition::matches(
+            "embedding_status",
+            EMBEDDING_STATUS_PENDING.to_string(),
+        )]);
+
+        let mut memos = Vec::new();
+        let mut offset: Option<PointId> = None;
+        let page_size = 100u32;
+
+        loop {
+            let mut scroll_builder = ScrollPointsBuilder::new(collection)
+                .filter(filter.clone())
+                .with_payload(true)
+                .with_vectors(true)
+                .limit(page_size);
+
+            if let Some(ref off) = offset {
+                scroll_builder = scroll_builder.offset(off.clone());
+            }
+
+            let result = self
+                .client
+                .scroll(scroll_builder)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+            for point in &result.result {
+                let id = self.extract_uuid_from_point_id(&point.id)?;
+                let memo = self.payload_to_memo(id, &point.payload)?;
+                memos.push((memo, self.extract_vector_from_point(point)));
+            }
+
+            match result.next_page_offset {
+                Some(next_offset) => offset = Some(next_offset),
+                None => break,
+            }
+        }
+
+        Ok(memos)
+    }
+
     pub async fn get_memo(&self, id: Uuid, demo: bool) -> Result<Option<Memo>> {
         let collection = if demo {
             COLLECTION_MEMOS_DEMO
@@ -116,19 +692,8 @@ impl QdrantService {
         self.insert_memo(memo, vector, demo).await
     }
 
-    #[allow(deprecated)]
     fn extract_vector_from_point(&self, point: &qdrant_client::qdrant::RetrievedPoint) -> Vec<f32> {
-        point
-            .vectors
-            .as_ref()
-            .and_then(|v| v.vectors_options.as_ref())
-            .and_then(|v| match v {
-                qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vec) => {
-                    Some(vec.data.clone())
-                }
-                _ => None,
-            })
-            .unwrap_or_default()
+        vector_from_output(&point.vectors)
     }
 
     async fn get_memo_point(
@@ -157,6 +722,101 @@ impl QdrantService {
         Ok(points.result.into_iter().next())
     }
 
+    /// 指定したメモの `tags` だけを書き換える。ベクトルや他のフィールドはそのまま
+    pub async fn set_memo_tags(&self, ids: &[Uuid], tags: &[String], demo: bool) -> Result<()> {
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("tags".into(), tags.to_vec().into());
+        self.set_memo_payload(ids, payload, demo).await
+    }
+
+    /// アーカイブ済みのメモのタグだけを書き換える
+    pub async fn set_archived_memo_tags(&self, ids: &[Uuid], tags: &[String]) -> Result<()> {
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("tags".into(), tags.to_vec().into());
+        self.set_memo_payload_in(COLLECTION_MEMOS_ARCHIVE, ids, payload)
+            .await
+    }
+
+    /// 完了フラグだけを書き換える (ベクトルはそのまま)
+    pub async fn set_memo_completed(&self, id: Uuid, completed: bool, demo: bool) -> Result<()> {
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("completed".into(), completed.into());
+        self.set_memo_payload(&[id], payload, demo).await
+    }
+
+    /// 繰り返しメモの完了した回だけを書き換える
+    pub async fn set_completed_occurrences(
+        &self,
+        id: Uuid,
+        dates: &[NaiveDate],
+        demo: bool,
+    ) -> Result<()> {
+        let dates: Vec<String> = dates.iter().map(NaiveDate::to_string).collect();
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert(COMPLETED_OCCURRENCES.into(), dates.into());
+        self.set_memo_payload(&[id], payload, demo).await
+    }
+
+    async fn set_memo_payload(
+        &self,
+        ids: &[Uuid],
+        payload: HashMap<String, Value>,
+        demo: bool,
+    ) -> Result<()> {
+        let collection = if demo {
+            COLLECTION_MEMOS_DEMO
+        } else {
+            COLLECTION_MEMOS
+        };
+
+        self.set_memo_payload_in(collection, ids, payload).await
+    }
+
+    async fn set_memo_payload_in(
+        &self,
+        collection: &str,
+        ids: &[Uuid],
+        payload: HashMap<String, Value>,
+    ) -> Result<()> {
+        let _writes = self.writes.read().await;
+        let filter = Filter::must([Condition::has_id(ids.iter().map(|id| id.to_string()))]);
+        self.client
+            .set_payload(
+                SetPayloadPointsBuilder::new(collection, payload)
+                    .points_selector(filter)
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// メモをベクトルごと `memos_archive` に移す
+    pub async fn archive_memo(&self, id: Uuid, demo: bool) -> Result<()> {
+        let _writes = self.writes.read().await;
+        let point = self
+            .get_memo_point(id, demo)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Memo {} not found", id)))?;
+
+        let vector = self.extract_vector_from_point(&point);
+        let archived = PointStruct::new(id.to_string(), vector, point.payload);
+        self.client
+            .upsert_points(
+                UpsertPointsBuilder::new(COLLECTION_MEMOS_ARCHIVE, vec![archived]).wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        let collection = if demo {
+            COLLECTION_MEMOS_DEMO
+        } else {
+            COLLECTION_MEMOS
+        };
+        self.delete_memos_in(collection, &[id]).await
+    }
+
     pub async fn delete_memo(&self, id: Uuid, demo: bool) -> Result<()> {
         let collection = if demo {
             COLLECTION_MEMOS_DEMO
@@ -164,6 +824,7 @@ impl QdrantService {
             COLLECTION_MEMOS
         };
 
+        let _writes = self.writes.read().await;
         let point_id: PointId = id.to_string().into();
 
         self.client
@@ -184,74 +845,210 @@ impl QdrantService {
         limit: u32,
         demo: bool,
     ) -> Result<Vec<SearchResult>> {
+        self.search_excluding(vector, filters, &[], limit, demo).await
+    }
+
+    /// 指定したメモを結果から除いて検索する
+    pub async fn search_excluding(
+        &self,
+        vector: Vec<f32>,
+        filters: &SearchFilters,
+        exclude: &[Uuid],
+        limit: u32,
+        demo: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let hits = self
+            .search_points(vector, filters, exclude, limit, false, demo)
+            .await?;
+        Ok(hits.into_iter().map(|(result, _)| result).collect())
+    }
+
+    /// 検索結果をメモのベクトル付きで返す (MMRなどの再ランキング用)
+    pub async fn search_with_vectors(
+        &self,
+        vector: Vec<f32>,
+        filters: &SearchFilters,
+        limit: u32,
+        demo: bool,
+    ) -> Result<Vec<(SearchResult, Vec<f32>)>> {
+        let hits = self
+            .search_points(vector, filters, &[], limit, true, demo)
+            .await?;
+        Ok(hits
+            .into_iter()
+            .map(|(result, vector)| (result, vector.unwrap_or_default()))
+            .collect())
+    }
+
+    async fn search_points(
+        &self,
+        vector: Vec<f32>,
+        filters: &SearchFilters,
+        exclude: &[Uuid],
+        limit: u32,
+        with_vectors: bool,
+        demo: bool,
+    ) -> Result<Vec<(SearchResult, Option<Vec<f32>>)>> {
         let collection = if demo {
             COLLECTION_MEMOS_DEMO
         } else {
             COLLECTION_MEMOS
         };
 
-        let filter = self.build_filter(filters);
-
-        let mut search_builder =
-            SearchPointsBuilder::new(collection, vector, limit as u64).with_payload(true);
-
-        if let Some(f) = filter {
-            search_builder = search_builder.filter(f);
+        let mut filter = self.build_filter(filters).unwrap_or_default();
+        filter.must_not.push(Condition::matches(
+            "embedding_status",
+            EMBEDDING_STATUS_PENDING.to_string(),
+        ));
+        if !exclude.is_empty() {
+            filter.must_not.push(Condition::has_id(
+                exclude.iter().map(|id| id.to_string()),
+            ));
         }
 
+        let search_builder = SearchPointsBuilder::new(collection, vector, limit as u64)
+            .with_payload(true)
+            .with_vectors(with_vectors)
+            .filter(filter);
+
         let results = self
             .client
             .search_points(search_builder)
             .await
             .map_err(|e| AppError::Qdrant(e.to_string()))?;
 
-        results
-            .result
-            .into_iter()
-            .map(|point| {
-                let id = self.extract_uuid_from_point_id(&point.id)?;
-
-                let content = self.get_string_field(&point.payload, "content")?;
-                let tags = self.get_string_array_field(&point.payload, "tags")?;
-                let from = self.get_optional_date_field(&point.payload, "from")?;
-                let date_added = self.get_datetime_field(&point.payload, "date_added")?;
+        let mut hits = Vec::new();
+        for point in results.result {
+            let id = self.extract_uuid_from_point_id(&point.id)?;
+            if !self.payload_occurs_in(filters, id, &point.payload)? {
+                continue;
+            }
+            hits.push((id, point));
+        }
 
+        hits.into_iter()
+            .map(|(id, point)| {
                 // スコア変換: ベースライン0.77を基準に0-1にスケール、指数変換で高スコアを強調
                 let baseline = 0.77_f32;
                 let linear = ((point.score - baseline) / (1.0 - baseline)).clamp(0.0, 1.0);
                 let normalized_score = linear.powf(0.5);
 
-                Ok(SearchResult {
-                    id,
-                    content,
-                    score: normalized_score,
-                    tags,
-                    from,
-                    date_added,
-                })
+                let vector = with_vectors.then(|| vector_from_output(&point.vectors));
+                let result = self.payload_to_search_result(id, &point.payload, normalized_score)?;
+                Ok((result, vector))
+            })
+            .collect()
+    }
+
+    /// コサイン類似度 (正規化前の生スコア) が `min_similarity` 以上の近傍メモを返す
+    pub async fn nearest_memos(
+        &self,
+        vector: Vec<f32>,
+        limit: u32,
+        min_similarity: f32,
+        demo: bool,
+    ) -> Result<Vec<(Memo, f32)>> {
+        let collection = if demo {
+            COLLECTION_MEMOS_DEMO
+        } else {
+            COLLECTION_MEMOS
+        };
+        let filter = Filter::must_not([Condition::matches(
+            "embedding_status",
+            EMBEDDING_STATUS_PENDING.to_string(),
+        )]);
+
+        let results = self
+            .client
+            .search_points(
+                SearchPointsBuilder::new(collection, vector, limit as u64)
+                    .with_payload(true)
+                    .filter(filter)
+                    .score_threshold(min_similarity),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        results
+            .result
+            .into_iter()
+            .map(|point| {
+                let id = self.extract_uuid_from_point_id(&point.id)?;
+                Ok((self.payload_to_memo(id, &point.payload)?, point.score))
             })
             .collect()
     }
 
-    pub async fn get_all_tags(&self, demo: bool) -> Result<Vec<String>> {
-        use qdrant_client::qdrant::ScrollPointsBuilder;
-        use std::collections::HashSet;
+    /// 保存済みのベクトルを使って、そのメモに似たメモを探す (メモ自身は除く)
+    pub async fn related_memos(
+        &self,
+        id: Uuid,
+        filters: &SearchFilters,
+        limit: u32,
+        demo: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let point = self
+            .get_memo_point(id, demo)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Memo {} not found", id)))?;
+
+        if matches!(
+            self.get_string_field(&point.payload, "embedding_status"),
+            Ok(status) if status == EMBEDDING_STATUS_PENDING
+        ) {
+            return Err(AppError::BadRequest(format!(
+                "Memo {} has not been embedded yet",
+                id
+            )));
+        }
+
+        let vector = self.extract_vector_from_point(&point);
+        self.search_excluding(vector, filters, &[id], limit, demo).await
+    }
 
+    /// payloadの `content` を部分一致で走査する。Embedder停止時のフォールバックと、
+    /// ベクトル未確定のメモ (`pending_only`) の検索に使う
+    pub async fn lexical_search(
+        &self,
+        query: &str,
+        filters: &SearchFilters,
+        limit: u32,
+        demo: bool,
+        pending_only: bool,
+    ) -> Result<Vec<SearchResult>> {
         let collection = if demo {
             COLLECTION_MEMOS_DEMO
         } else {
             COLLECTION_MEMOS
         };
 
-        let mut all_tags: HashSet<String> = HashSet::new();
+        let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut filter = self.build_filter(filters);
+        if pending_only {
+            filter
+                .get_or_insert_with(Filter::default)
+                .must
+                .push(Condition::matches(
+                    "embedding_status",
+                    EMBEDDING_STATUS_PENDING.to_string(),
+                ));
+        }
+        let mut results = Vec::new();
         let mut offset: Option<PointId> = None;
-        let limit = 100u32;
+        let page_size = 100u32;
 
         loop {
             let mut scroll_builder = ScrollPointsBuilder::new(collection)
                 .with_payload(true)
-                .limit(limit);
+                .limit(page_size);
 
+            if let Some(ref f) = filter {
+                scroll_builder = scroll_builder.filter(f.clone());
+            }
             if let Some(ref off) = offset {
                 scroll_builder = scroll_builder.offset(off.clone());
             }
@@ -263,29 +1060,108 @@ impl QdrantService {
                 .map_err(|e| AppError::Qdrant(e.to_string()))?;
 
             for point in &result.result {
-                if let Ok(tags) = self.get_string_array_field(&point.payload, "tags") {
-                    for tag in tags {
-                        all_tags.insert(tag);
-                    }
+                let Ok(content) = self.get_string_field(&point.payload, "content") else {
+                    continue;
+                };
+                let id = self.extract_uuid_from_point_id(&point.id)?;
+                if !self.payload_occurs_in(filters, id, &point.payload)? {
+                    continue;
                 }
+                let content = content.to_lowercase();
+                let matched = terms
+                    .iter()
+                    .filter(|t| content.contains(t.as_str()))
+                    .count();
+                if matched == 0 {
+                    continue;
+                }
+
+                let score = matched as f32 / terms.len() as f32;
+                results.push(self.payload_to_search_result(id, &point.payload, score)?);
             }
 
             match result.next_page_offset {
                 Some(next_offset) => offset = Some(next_offset),
                 None => break,
             }
-
-            if result.result.len() < limit as usize {
-                break;
-            }
         }
 
-        let mut tags: Vec<String> = all_tags.into_iter().collect();
-        tags.sort();
-        Ok(tags)
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.date_added.cmp(&a.date_added))
+        });
+        results.truncate(limit as usize);
+
+        Ok(results)
+    }
+
+    /// iCalendar から取り込んだメモを UID ごとに取得する
+    pub async fn imported_memos(&self, demo: bool) -> Result<HashMap<String, Memo>> {
+        let filter = Filter::must_not([Condition::is_empty(ICS_UID)]);
+        let memos = self.scroll_memos(Some(filter), demo).await?;
+        Ok(memos
+            .into_iter()
+            .filter_map(|memo| Some((memo.ics_uid.clone()?, memo)))
+            .collect())
+    }
+
+    /// `SearchFilters` に一致するメモを全件取得する (順序は不定)
+    pub async fn matching_memos(&self, filters: &SearchFilters, demo: bool) -> Result<Vec<Memo>> {
+        let mut memos = self.scroll_memos(self.build_filter(filters), demo).await?;
+        memos.retain(|memo| self.occurs_in(filters, memo));
+        Ok(memos)
+    }
+
+    /// 自由文が無いクエリ用。フィルタに一致するメモを新しい順に返す
+    pub async fn filtered_memos(
+        &self,
+        filters: &SearchFilters,
+        limit: u32,
+        demo: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let mut memos = self.matching_memos(filters, demo).await?;
+        memos.sort_by_key(|m| std::cmp::Reverse(m.date_added));
+        memos.truncate(limit as usize);
+
+        Ok(memos
+            .into_iter()
+            .map(|memo| SearchResult {
+                id: memo.id,
+                content: memo.content,
+                score: 0.0,
+                tags: memo.tags,
+                from: memo.from,
+                date_added: memo.date_added,
+                completed: memo.completed,
+            })
+            .collect())
     }
 
-    fn extract_uuid_from_point_id(&self, point_id: &Option<PointId>) -> Result<Uuid> {
+    fn payload_to_search_result(
+        &self,
+        id: Uuid,
+        payload: &HashMap<String, Value>,
+        score: f32,
+    ) -> Result<SearchResult> {
+        let content = self.get_string_field(payload, "content")?;
+        let tags = self.get_string_array_field(payload, "tags")?;
+        let from = self.get_optional_date_field(payload, "from")?;
+        let date_added = self.get_datetime_field(payload, "date_added")?;
+        let completed = self.get_bool_field(payload, "completed")?;
+
+        Ok(SearchResult {
+            id,
+            content,
+            score,
+            tags,
+            from,
+            date_added,
+            completed,
+        })
+    }
+
+    pub(crate) fn extract_uuid_from_point_id(&self, point_id: &Option<PointId>) -> Result<Uuid> {
         let point_id = point_id
             .as_ref()
             .ok_or_else(|| AppError::Qdrant("Missing point ID".into()))?;
@@ -300,6 +1176,37 @@ impl QdrantService {
         }
     }
 
+    /// 繰り返しメモは Qdrant では系列の期間でしか絞り込めないので、
+    /// 日付フィルタに一致する回が実際にあるかを確かめる。繰り返しでないメモは常に true
+    fn occurs_in(&self, filters: &SearchFilters, memo: &Memo) -> bool {
+        let (Some(recurrence), Some(from)) = (&memo.recurrence, memo.from) else {
+            return true;
+        };
+        let window = self.calendar.filter_window(filters);
+        if window.is_unbounded() {
+            return true;
+        }
+        recurrence.occurs_within(
+            from,
+            memo.until,
+            filters.date_match,
+            &window,
+            memo.timezone.unwrap_or(self.calendar.timezone),
+        )
+    }
+
+    fn payload_occurs_in(
+        &self,
+        filters: &SearchFilters,
+        id: Uuid,
+        payload: &HashMap<String, Value>,
+    ) -> Result<bool> {
+        if !payload.contains_key(RECURRENCE) {
+            return Ok(true);
+        }
+        Ok(self.occurs_in(filters, &self.payload_to_memo(id, payload)?))
+    }
+
     fn build_filter(&self, filters: &SearchFilters) -> Option<Filter> {
         let mut conditions = Vec::new();
 
@@ -311,40 +1218,19 @@ impl QdrantService {
             conditions.push(Condition::matches("type", type_str.to_string()));
         }
 
-        if let Some(from_gte) = filters.from_gte {
-            conditions.push(Condition::range(
-                "from_ts",
-                qdrant_client::qdrant::Range {
-                    gte: Some(
-                        from_gte
-                            .and_hms_opt(0, 0, 0)
-                            .unwrap()
-                            .and_utc()
-                            .timestamp() as f64,
-                    ),
-                    ..Default::default()
-                },
-            ));
+        let window = self.calendar.filter_window(filters);
+        conditions.extend(date_conditions(filters.date_match, &window));
+
+        for tag in &filters.tags {
+            conditions.push(Condition::matches("tags", tag.clone()));
         }
 
-        if let Some(until_lte) = filters.until_lte {
-            conditions.push(Condition::range(
-                "until_ts",
-                qdrant_client::qdrant::Range {
-                    lte: Some(
-                        until_lte
-                            .and_hms_opt(23, 59, 59)
-                            .unwrap()
-                            .and_utc()
-                            .timestamp() as f64,
-                    ),
-                    ..Default::default()
-                },
-            ));
+        if let Some(completed) = filters.completed {
+            conditions.push(Condition::matches("completed", completed));
         }
 
-        for tag in &filters.tags {
-            conditions.push(Condition::matches("tags", tag.clone()));
+        for phrase in &filters.phrases {
+            conditions.push(Condition::matches_text("content", phrase.clone()));
         }
 
         if conditions.is_empty() {
@@ -371,26 +1257,81 @@ impl QdrantService {
             memo.last_accessed.to_rfc3339().into(),
         );
         payload.insert("completed".into(), memo.completed.into());
+        payload.insert(
+            "embedding_status".into(),
+            match memo.embedding_status {
+                EmbeddingStatus::Ready => "ready",
+                EmbeddingStatus::Pending => EMBEDDING_STATUS_PENDING,
+            }
+            .into(),
+        );
+
+        let timezone = memo.timezone.unwrap_or(self.calendar.timezone);
+        if let Some(tz) = memo.timezone {
+            payload.insert(TIMEZONE.into(), tz.name().into());
+        }
 
         if let Some(from) = memo.from {
             payload.insert("from".into(), from.to_string().into());
-            payload.insert(
-                "from_ts".into(),
-                (from.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as i64).into(),
-            );
+            payload.insert("from_ts".into(), start_timestamp(from, timezone).into());
+        }
+
+        if let Some(ref uid) = memo.ics_uid {
+            payload.insert(ICS_UID.into(), uid.clone().into());
+        }
+
+        if memo.from.is_some() || memo.until.is_some() {
+            let single_day = match (memo.from, memo.until) {
+                (Some(from), Some(until)) => from.date == until.date,
+                _ => true,
+            };
+            payload.insert(SINGLE_DAY.into(), single_day.into());
         }
 
         if let Some(until) = memo.until {
             payload.insert("until".into(), until.to_string().into());
-            payload.insert(
-                "until_ts".into(),
-                (until
-                    .and_hms_opt(23, 59, 59)
-                    .unwrap()
-                    .and_utc()
-                    .timestamp() as i64)
-                    .into(),
-            );
+            payload.insert("until_ts".into(), end_timestamp(until, timezone).into());
+        }
+
+        // 日付だけのメモは暦日で比べる (タイムゾーンに依存しない)
+        let all_day = memo
+            .from
+            .iter()
+            .chain(&memo.until)
+            .all(|d| d.time.is_none());
+        if all_day && (memo.from.is_some() || memo.until.is_some()) {
+            payload.insert(ALL_DAY.into(), true.into());
+            if let Some(from) = memo.from {
+                payload.insert(FROM_DAY.into(), day_number(from.date).into());
+            }
+            if let Some(until) = memo.until {
+                payload.insert(UNTIL_DAY.into(), day_number(until.date).into());
+            }
+        }
+
+        payload.insert(RECURRING.into(), memo.recurrence.is_some().into());
+        if let (Some(recurrence), Some(from)) = (&memo.recurrence, memo.from) {
+            payload.insert(RECURRENCE.into(), recurrence.to_string().into());
+            let dates: Vec<String> = memo
+                .completed_occurrences
+                .iter()
+                .map(NaiveDate::to_string)
+                .collect();
+            payload.insert(COMPLETED_OCCURRENCES.into(), dates.into());
+
+            // until_ts / until_day は系列の最終回の終わり。終わりの無い系列には付けない
+            payload.remove("until_ts");
+            payload.remove(UNTIL_DAY);
+            let last = memo.until.unwrap_or(from);
+            if let Some(series_end) = recurrence.series_end(from.date, Some(last.date)) {
+                payload.insert(
+                    "until_ts".into(),
+                    end_timestamp(last.with_date(series_end), timezone).into(),
+                );
+                if all_day {
+                    payload.insert(UNTIL_DAY.into(), day_number(series_end).into());
+                }
+            }
         }
 
         payload
@@ -413,6 +1354,29 @@ impl QdrantService {
         let completed = self.get_bool_field(payload, "completed")?;
         let from = self.get_optional_date_field(payload, "from")?;
         let until = self.get_optional_date_field(payload, "until")?;
+        let timezone = match self.get_string_field(payload, TIMEZONE) {
+            Ok(name) => Some(name.parse().map_err(|e| {
+                AppError::Qdrant(format!("Invalid timezone for memo {}: {}", id, e))
+            })?),
+            Err(_) => None,
+        };
+        let recurrence = match self.get_string_field(payload, RECURRENCE) {
+            Ok(rule) => Some(rule.parse::<Recurrence>().map_err(|e| {
+                AppError::Qdrant(format!("Invalid recurrence for memo {}: {}", id, e))
+            })?),
+            Err(_) => None,
+        };
+        let completed_occurrences = self
+            .get_string_array_field(payload, COMPLETED_OCCURRENCES)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .collect();
+        // embedding_status 導入前のデータはベクトル化済みとみなす
+        let embedding_status = match self.get_string_field(payload, "embedding_status") {
+            Ok(s) if s == EMBEDDING_STATUS_PENDING => EmbeddingStatus::Pending,
+            _ => EmbeddingStatus::Ready,
+        };
 
         Ok(Memo {
             id,
@@ -420,15 +1384,20 @@ impl QdrantService {
             memo_type,
             from,
             until,
+            timezone,
             tags,
             date_added,
             access_count,
             last_accessed,
             completed,
+            embedding_status,
+            ics_uid: self.get_string_field(payload, ICS_UID).ok(),
+            recurrence,
+            completed_occurrences,
         })
     }
 
-    fn get_string_field(&self, payload: &HashMap<String, Value>, key: &str) -> Result<String> {
+    pub(crate) fn get_string_field(&self, payload: &HashMap<String, Value>, key: &str) -> Result<String> {
         payload
             .get(key)
             .and_then(|v| v.kind.as_ref())
@@ -463,7 +1432,7 @@ impl QdrantService {
             .ok_or_else(|| AppError::Qdrant(format!("Missing or invalid field: {}", key)))
     }
 
-    fn get_int_field(&self, payload: &HashMap<String, Value>, key: &str) -> Result<i64> {
+    pub(crate) fn get_int_field(&self, payload: &HashMap<String, Value>, key: &str) -> Result<i64> {
         payload
             .get(key)
             .and_then(|v| v.kind.as_ref())
@@ -485,7 +1454,7 @@ impl QdrantService {
             .ok_or_else(|| AppError::Qdrant(format!("Missing or invalid field: {}", key)))
     }
 
-    fn get_datetime_field(
+    pub(crate) fn get_datetime_field(
         &self,
         payload: &HashMap<String, Value>,
         key: &str,
@@ -496,22 +1465,73 @@ impl QdrantService {
             .map_err(|e| AppError::Qdrant(format!("Invalid datetime for {}: {}", key, e)))
     }
 
+    /// `2026-01-20` (時刻導入前の形式) と `2026-01-20T15:00` のどちらも読める
     fn get_optional_date_field(
         &self,
         payload: &HashMap<String, Value>,
         key: &str,
-    ) -> Result<Option<NaiveDate>> {
+    ) -> Result<Option<MemoDate>> {
         match payload.get(key) {
             None => Ok(None),
             Some(v) => match v.kind.as_ref() {
-                Some(qdrant_client::qdrant::value::Kind::StringValue(s)) => {
-                    NaiveDate::parse_from_str(s, "%Y-%m-%d")
-                        .map(Some)
-                        .map_err(|e| AppError::Qdrant(format!("Invalid date for {}: {}", key, e)))
-                }
+                Some(qdrant_client::qdrant::value::Kind::StringValue(s)) => s
+                    .parse::<MemoDate>()
+                    .map(Some)
+                    .map_err(|e| AppError::Qdrant(format!("Invalid date for {}: {}", key, e))),
                 Some(qdrant_client::qdrant::value::Kind::NullValue(_)) => Ok(None),
                 _ => Ok(None),
             },
         }
     }
 }
+
+fn vector_from_output(vectors: &Option<qdrant_client::qdrant::VectorsOutput>) -> Vec<f32> {
+    vectors
+        .as_ref()
+        .and_then(|v| v.vectors_options.as_ref())
+        .and_then(|v| match v {
+            qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vec) => {
+                match vec.clone().into_vector() {
+                    qdrant_client::qdrant::vector_output::Vector::Dense(dense) => Some(dense.data),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// 取得したポイントのベクトルを書き込み用に変換する。名前付き・疎・マルチベクトルもそのまま引き継ぐ
+fn vectors_for_upsert(vectors: Option<VectorsOutput>) -> Vectors {
+    match vectors.and_then(|v| v.vectors_options) {
+        Some(VectorsOptions::Vector(vector)) => vector_for_upsert(vector).into(),
+        Some(VectorsOptions::Vectors(named)) => named
+            .vectors
+            .into_iter()
+            .map(|(name, vector)| (name, vector_for_upsert(vector)))
+            .collect::<HashMap<String, Vector>>()
+            .into(),
+        None => HashMap::<String, Vector>::new().into(),
+    }
+}
+
+fn vector_for_upsert(vector: VectorOutput) -> Vector {
+    match vector.into_vector() {
+        vector_output::Vector::Dense(dense) => Vector::new_dense(dense.data),
+        vector_output::Vector::Sparse(sparse) => Vector::new_sparse(sparse.indices, sparse.values),
+        vector_output::Vector::MultiDense(multi) => Vector::new_multi(
+            multi
+                .vectors
+                .into_iter()
+                .map(|v| v.data)
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+fn model_metadata(model: &EmbeddingModel) -> HashMap<String, serde_json::Value> {
+    HashMap::from([
+        ("embedding_model".to_string(), model.id.clone().into()),
+        ("embedding_dim".to_string(), model.dimension.into()),
+    ])
+}
@@ -0,0 +1,399 @@
+use std::collections::VecDeque;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use chrono_tz::Tz;
+
+use crate::models::{DateMatch, Frequency, MemoDate, Recurrence};
+use crate::services::calendar::{day_number, end_timestamp, start_timestamp, DateWindow};
+use crate::services::date_match::span_matches;
+
+/// 候補日が1つも無い周期がこれだけ続いたら打ち切る (INTERVAL=12 で2月30日を指すような規則)
+const MAX_EMPTY_PERIODS: u32 = 1000;
+/// 1回の判定で調べる回数の上限。毎日の繰り返しでおよそ270年分
+const MAX_SCANNED_OCCURRENCES: usize = 100_000;
+
+impl Recurrence {
+    /// 各回の初日を日付順に返す。初回 `start` は規則に合わなくても1回目に数える (RFC 5545 の DTSTART と同じ)
+    pub fn occurrences(&self, start: NaiveDate) -> Occurrences<'_> {
+        Occurrences {
+            rule: self,
+            start,
+            period: 0,
+            pending: VecDeque::from([start]),
+            emitted: 0,
+            last: None,
+            empty_periods: 0,
+            done: false,
+        }
+    }
+
+    /// 最終回の初日。COUNT / UNTIL の無い系列と、調べる回数の上限を超える系列は None (終わりの無い系列と同じ扱い)
+    pub fn last_occurrence(&self, start: NaiveDate) -> Option<NaiveDate> {
+        if self.count.is_none() && self.until.is_none() {
+            return None;
+        }
+        let mut occurrences = self.occurrences(start);
+        let last = occurrences.by_ref().take(MAX_SCANNED_OCCURRENCES).last();
+        match occurrences.next() {
+            Some(_) => None,
+            None => last,
+        }
+    }
+
+    /// 系列の最終回の最終日。各回は初回 (`first`〜`last`) と同じ日数。終わりの無い系列は None
+    pub fn series_end(&self, first: NaiveDate, last: Option<NaiveDate>) -> Option<NaiveDate> {
+        let span = occurrence_span(first, last);
+        self.last_occurrence(first)
+            .and_then(|date| date.checked_add_days(Days::new(span)))
+    }
+
+    /// `date` が `start` から始まる系列のいずれかの回の初日か
+    pub fn is_occurrence(&self, start: NaiveDate, date: NaiveDate) -> bool {
+        self.occurrences(start)
+            .take(MAX_SCANNED_OCCURRENCES)
+            .take_while(|d| *d <= date)
+            .any(|d| d == date)
+    }
+
+    /// いずれかの回が検索区間 `window` に `mode` で一致するか。
+    /// 各回は初回 (`first`〜`last`) と同じ日数・同じ時刻の期間を持つ。
+    /// 日付だけの系列は暦日で、時刻付きの系列は `timezone` での瞬間で比べる
+    pub fn occurs_within(
+        &self,
+        first: MemoDate,
+        last: Option<MemoDate>,
+        mode: DateMatch,
+        window: &DateWindow,
+        timezone: Tz,
+    ) -> bool {
+        let span = occurrence_span(first.date, last.map(|d| d.date));
+        let single_day = span == 0;
+        if mode == DateMatch::SingleDay && !single_day {
+            return false;
+        }
+        let last = last.unwrap_or(first);
+        let all_day = first.time.is_none() && last.time.is_none();
+        let (start, end) = if all_day {
+            (window.start_day(), window.end_day())
+        } else {
+            (window.start_ts(), window.end_ts())
+        };
+
+        for date in self.occurrences(first.date).take(MAX_SCANNED_OCCURRENCES) {
+            let Some(last_day) = date.checked_add_days(Days::new(span)) else {
+                return false;
+            };
+            let (first_value, last_value) = if all_day {
+                (day_number(date), day_number(last_day))
+            } else {
+                (
+                    start_timestamp(first.with_date(date), timezone),
+                    end_timestamp(last.with_date(last_day), timezone),
+                )
+            };
+            if end.is_some_and(|end| first_value > end) {
+                return false;
+            }
+            if span_matches(mode, start, end, first_value, last_value, single_day) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// 各回の日数 - 1 (初回の from〜until と同じ)
+pub fn occurrence_span(first: NaiveDate, last: Option<NaiveDate>) -> u64 {
+    last.map_or(0, |last| (last - first).num_days().max(0) as u64)
+}
+
+/// `Recurrence::occurrences` のイテレータ。周期 (日/週/月/年) ごとに候補日を作って順に返す
+pub struct Occurrences<'a> {
+    rule: &'a Recurrence,
+    start: NaiveDate,
+    /// 次に候補日を作る周期 (0 が初回を含む周期)
+    period: u32,
+    pending: VecDeque<NaiveDate>,
+    emitted: u32,
+    last: Option<NaiveDate>,
+    empty_periods: u32,
+    done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = NaiveDate;
+
+    fn next(&mut self) -> Option<NaiveDate> {
+        while !self.done {
+            let Some(date) = self.pending.pop_front() else {
+                self.fill_next_period();
+                continue;
+            };
+            if self.last.is_some_and(|last| date <= last) {
+                continue;
+            }
+            if self.rule.until.is_some_and(|until| date > until)
+                || self.rule.count.is_some_and(|count| self.emitted >= count)
+            {
+                self.done = true;
+                break;
+            }
+
+            self.emitted += 1;
+            self.last = Some(date);
+            return Some(date);
+        }
+        None
+    }
+}
+
+impl Occurrences<'_> {
+    fn fill_next_period(&mut self) {
+        let Some(step) = self.period.checked_mul(self.rule.interval) else {
+            self.done = true;
+            return;
+        };
+        self.period += 1;
+
+        let Some(mut dates) = self.period_dates(step) else {
+            // 日付の範囲を超えた
+            self.done = true;
+            return;
+        };
+        dates.retain(|d| *d >= self.start);
+        dates.sort();
+        dates.dedup();
+
+        if dates.is_empty() {
+            self.empty_periods += 1;
+            self.done = self.empty_periods >= MAX_EMPTY_PERIODS;
+        } else {
+            self.empty_periods = 0;
+            self.pending.extend(dates);
+        }
+    }
+
+    /// 初回から `step` 単位後の周期に入る候補日
+    fn period_dates(&self, step: u32) -> Option<Vec<NaiveDate>> {
+        let rule = self.rule;
+        let start = self.start;
+        match rule.frequency {
+            Frequency::Daily => {
+                let date = start.checked_add_days(Days::new(step as u64))?;
+                let matches = rule.by_day.is_empty()
+                    || rule.by_day.iter().any(|d| d.weekday == date.weekday());
+                Some(if matches { vec![date] } else { Vec::new() })
+            }
+            Frequency::Weekly => {
+                let week = start
+                    .week(rule.week_start)
+                    .first_day()
+                    .checked_add_days(Days::new(step as u64 * 7))?;
+                let weekdays: Vec<_> = if rule.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    rule.by_day.iter().map(|d| d.weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .map(|weekday| {
+                        let offset = (weekday.num_days_from_monday() + 7
+                            - rule.week_start.num_days_from_monday())
+                            % 7;
+                        week.checked_add_days(Days::new(offset as u64))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let month = start
+                    .with_day(1)
+                    .expect("day 1 always exists")
+                    .checked_add_months(Months::new(step))?;
+                let last_day = month.checked_add_months(Months::new(1))?.pred_opt()?.day() as i32;
+                let day_of_month = |day: i32| {
+                    let day = if day < 0 { last_day + 1 + day } else { day };
+                    (1..=last_day)
+                        .contains(&day)
+                        .then(|| month.with_day(day as u32))
+                        .flatten()
+                };
+
+                let dates = if !rule.by_month_day.is_empty() {
+                    rule.by_month_day
+                        .iter()
+                        .filter_map(|&day| day_of_month(day as i32))
+                        .collect()
+                } else if !rule.by_day.is_empty() {
+                    rule.by_day
+                        .iter()
+                        .flat_map(|rule| {
+                            let days: Vec<NaiveDate> = (1..=last_day)
+                                .filter_map(day_of_month)
+                                .filter(|d| d.weekday() == rule.weekday)
+                                .collect();
+                            match rule.ordinal {
+                                None => days,
+                                Some(n) if n > 0 => {
+                                    days.get(n as usize - 1).copied().into_iter().collect()
+                                }
+                                Some(n) => days
+                                    .len()
+                                    .checked_sub(n.unsigned_abs() as usize)
+                                    .and_then(|i| days.get(i).copied())
+                                    .into_iter()
+                                    .collect(),
+                            }
+                        })
+                        .collect()
+                } else {
+                    day_of_month(start.day() as i32).into_iter().collect()
+                };
+                Some(dates)
+            }
+            Frequency::Yearly => {
+                let year = i32::try_from(step).ok()?.checked_add(start.year())?;
+                // 2月29日始まりはうるう年だけ
+                Some(
+                    NaiveDate::from_ymd_opt(year, start.month(), start.day())
+                        .into_iter()
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn expand(rule: &str, start: NaiveDate) -> Vec<NaiveDate> {
+        let rule: Recurrence = rule.parse().unwrap();
+        rule.occurrences(start).take(50).collect()
+    }
+
+    #[test]
+    fn start_counts_as_the_first_occurrence() {
+        // 2026-01-01 は木曜。規則に合わなくても1回目
+        assert_eq!(
+            expand("FREQ=WEEKLY;BYDAY=TU;COUNT=3", date(2026, 1, 1)),
+            vec![date(2026, 1, 1), date(2026, 1, 6), date(2026, 1, 13)]
+        );
+        // 規則に合う日から始めれば重複しない
+        assert_eq!(
+            expand("FREQ=WEEKLY;BYDAY=TU;COUNT=3", date(2026, 1, 6)),
+            vec![date(2026, 1, 6), date(2026, 1, 13), date(2026, 1, 20)]
+        );
+    }
+
+    #[test]
+    fn monthly_ordinal_and_last_weekday() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=2TU,-1FR;COUNT=5", date(2026, 1, 13)),
+            vec![
+                date(2026, 1, 13),
+                date(2026, 1, 30),
+                date(2026, 2, 10),
+                date(2026, 2, 27),
+                date(2026, 3, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_last_day_of_month() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYMONTHDAY=-1;COUNT=4", date(2026, 1, 31)),
+            vec![
+                date(2026, 1, 31),
+                date(2026, 2, 28),
+                date(2026, 3, 31),
+                date(2026, 4, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_on_the_31st_skips_short_months() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;COUNT=4", date(2026, 1, 31)),
+            vec![
+                date(2026, 1, 31),
+                date(2026, 3, 31),
+                date(2026, 5, 31),
+                date(2026, 7, 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn yearly_on_feb_29_only_in_leap_years() {
+        assert_eq!(
+            expand("FREQ=YEARLY;COUNT=3", date(2024, 2, 29)),
+            vec![date(2024, 2, 29), date(2028, 2, 29), date(2032, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn weekly_interval_depends_on_wkst() {
+        // RFC 5545 3.8.5.3 の例
+        assert_eq!(
+            expand(
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=MO",
+                date(1997, 8, 5)
+            ),
+            vec![
+                date(1997, 8, 5),
+                date(1997, 8, 10),
+                date(1997, 8, 19),
+                date(1997, 8, 24),
+            ]
+        );
+        assert_eq!(
+            expand(
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=SU",
+                date(1997, 8, 5)
+            ),
+            vec![
+                date(1997, 8, 5),
+                date(1997, 8, 17),
+                date(1997, 8, 19),
+                date(1997, 8, 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        assert_eq!(
+            expand("FREQ=DAILY;INTERVAL=3;UNTIL=20260107", date(2026, 1, 1)),
+            vec![date(2026, 1, 1), date(2026, 1, 4), date(2026, 1, 7)]
+        );
+    }
+
+    #[test]
+    fn last_and_is_occurrence() {
+        let rule: Recurrence = "FREQ=MONTHLY;BYDAY=2TU;COUNT=3".parse().unwrap();
+        assert_eq!(
+            rule.last_occurrence(date(2026, 1, 13)),
+            Some(date(2026, 3, 10))
+        );
+        assert!(rule.is_occurrence(date(2026, 1, 13), date(2026, 2, 10)));
+        assert!(!rule.is_occurrence(date(2026, 1, 13), date(2026, 2, 17)));
+        assert!(!rule.is_occurrence(date(2026, 1, 13), date(2026, 4, 14)));
+
+        let endless: Recurrence = "FREQ=DAILY".parse().unwrap();
+        assert_eq!(endless.last_occurrence(date(2026, 1, 1)), None);
+    }
+
+    #[test]
+    fn last_occurrence_gives_up_on_huge_series() {
+        let mut rule: Recurrence = "FREQ=DAILY".parse().unwrap();
+        rule.count = Some(u32::MAX);
+        assert_eq!(rule.last_occurrence(date(2026, 1, 1)), None);
+    }
+}
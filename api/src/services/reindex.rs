@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{EmbeddingModel, EmbeddingStatus, Memo};
+use crate::services::migrations;
+use crate::services::qdrant::LOGICAL_COLLECTIONS;
+use crate::services::{EmbedderClient, QdrantService};
+
+/// 書き込みを止める前に差分反映を繰り返す上限。残りは書き込みを止めて反映する
+const MAX_SYNC_PASSES: usize = 5;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReindexState {
+    Idle,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexStatus {
+    pub state: ReindexState,
+    pub model: Option<EmbeddingModel>,
+    pub collection: Option<String>,
+    pub processed: usize,
+    pub total: usize,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// 全メモを現在のEmbedderモデルで再ベクトル化し、新しいコレクションへエイリアスを張り替える
+#[derive(Clone)]
+pub struct ReindexJob {
+    status: Arc<RwLock<ReindexStatus>>,
+}
+
+impl ReindexJob {
+    pub fn new() -> Self {
+        Self {
+            status: Arc::new(RwLock::new(ReindexStatus {
+                state: ReindexState::Idle,
+                model: None,
+                collection: None,
+                processed: 0,
+                total: 0,
+                error: None,
+                started_at: None,
+                finished_at: None,
+            })),
+        }
+    }
+
+    pub fn status(&self) -> ReindexStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    pub fn start(&self, qdrant: QdrantService, embedder: EmbedderClient) -> Result<ReindexStatus> {
+        {
+            let mut status = self.status.write().unwrap();
+            if status.state == ReindexState::Running {
+                return Err(AppError::BadRequest("Re-index is already running".into()));
+            }
+            *status = ReindexStatus {
+                state: ReindexState::Running,
+                model: None,
+                collection: None,
+                processed: 0,
+                total: 0,
+                error: None,
+                started_at: Some(Utc::now()),
+                finished_at: None,
+            };
+        }
+
+        let job = self.clone();
+        tokio::spawn(async move {
+            let result = job.run(&qdrant, &embedder).await;
+            let mut status = job.status.write().unwrap();
+            status.finished_at = Some(Utc::now());
+            match result {
+                Ok(()) => {
+                    status.state = ReindexState::Completed;
+                    tracing::info!("Re-index completed");
+                }
+                Err(e) => {
+                    status.state = ReindexState::Failed;
+                    status.error = Some(e.to_string());
+                    tracing::error!("Re-index failed: {}", e);
+                }
+            }
+        });
+
+        Ok(self.status())
+    }
+
+    async fn run(&self, qdrant: &QdrantService, embedder: &EmbedderClient) -> Result<()> {
+        let model = embedder.model_info().await?;
+        self.status.write().unwrap().model = Some(model.clone());
+        tracing::info!("Re-indexing all collections with {}", model.id);
+
+        // モデルが変わるなら、張り替えまで新しいモデルのベクトルを今のコレクションに書かない
+        if model != qdrant.current_model() {
+            embedder.set_switching_model(true);
+        }
+
+        let mut targets = Vec::new();
+        for logical in LOGICAL_COLLECTIONS {
+            let target = format!(
+                "{}_v{}_{}",
+                logical,
+                migrations::latest_version(),
+                Utc::now().format("%Y%m%d%H%M%S")
+            );
+            if let Err(e) = qdrant.create_physical_collection(&target, &model).await {
+                discard_targets(qdrant, &targets).await;
+                return Err(e);
+            }
+            targets.push((logical, target, HashMap::new()));
+
+            let (_, target, copied) = targets.last_mut().unwrap();
+            if let Err(e) = self
+                .copy_memos(qdrant, embedder, logical, target, &model, copied)
+                .await
+            {
+                discard_targets(qdrant, &targets).await;
+                return Err(e);
+            }
+        }
+
+        // 最後の差分反映から張り替えまではメモの書き込みを止め、反映漏れを出さない
+        let _writes = qdrant.pause_writes().await;
+        for (logical, target, copied) in &mut targets {
+            if let Err(e) = self
+                .sync_changes(qdrant, embedder, logical, target, &model, copied)
+                .await
+            {
+                discard_targets(qdrant, &targets).await;
+                return Err(e);
+            }
+        }
+        for (logical, target, _) in &targets {
+            qdrant.swap_alias(logical, target).await?;
+        }
+
+        qdrant.set_current_model(model);
+        embedder.set_switching_model(false);
+        Ok(())
+    }
+
+    async fn copy_memos(
+        &self,
+        qdrant: &QdrantService,
+        embedder: &EmbedderClient,
+        source: &str,
+        target: &str,
+        model: &EmbeddingModel,
+        copied: &mut HashMap<Uuid, Memo>,
+    ) -> Result<()> {
+        let memos = qdrant.scroll_memos_in(source, None).await?;
+        {
+            let mut status = self.status.write().unwrap();
+            status.collection = Some(source.to_string());
+            status.processed = 0;
+            status.total = memos.len();
+        }
+
+        for memo in memos {
+            self.copy_memo(qdrant, embedder, target, memo, model, copied)
+                .await?;
+        }
+
+        // コピー中の変更を書き込みを止めずに追いかけ、止めてからの差分を小さくしておく
+        for _ in 0..MAX_SYNC_PASSES {
+            if !self
+                .sync_changes(qdrant, embedder, source, target, model, copied)
+                .await?
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// コピー後に追加・更新・削除されたメモを反映する。差分があれば true
+    async fn sync_changes(
+        &self,
+        qdrant: &QdrantService,
+        embedder: &EmbedderClient,
+        source: &str,
+        target: &str,
+        model: &EmbeddingModel,
+        copied: &mut HashMap<Uuid, Memo>,
+    ) -> Result<bool> {
+        let current = qdrant.scroll_memos_in(source, None).await?;
+        let current_ids: HashSet<Uuid> = current.iter().map(|m| m.id).collect();
+        let deleted: Vec<Uuid> = copied
+            .keys()
+            .filter(|id| !current_ids.contains(id))
+            .copied()
+            .collect();
+        let changed: Vec<Memo> = current
+            .into_iter()
+            .filter(|m| copied.get(&m.id) != Some(m))
+            .collect();
+        if changed.is_empty() && deleted.is_empty() {
+            return Ok(false);
+        }
+
+        self.status.write().unwrap().total += changed.len();
+        for memo in changed {
+            match copied.get(&memo.id) {
+                // 本文が同じならベクトルは作り直さない (アクセス数・タグ・完了などの変更)
+                Some(previous) if previous.content == memo.content => {
+                    let mut updated = memo.clone();
+                    updated.embedding_status = EmbeddingStatus::Ready;
+                    qdrant
+                        .overwrite_memo_payload_in(target, &updated, model)
+                        .await?;
+                    copied.insert(memo.id, memo);
+                    self.status.write().unwrap().processed += 1;
+                }
+                _ => {
+                    self.copy_memo(qdrant, embedder, target, memo, model, copied)
+                        .await?
+                }
+            }
+        }
+        if !deleted.is_empty() {
+            qdrant.delete_memos_in(target, &deleted).await?;
+            for id in &deleted {
+                copied.remove(id);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn copy_memo(
+        &self,
+        qdrant: &QdrantService,
+        embedder: &EmbedderClient,
+        target: &str,
+        mut memo: Memo,
+        model: &EmbeddingModel,
+        copied: &mut HashMap<Uuid, Memo>,
+    ) -> Result<()> {
+        let vector = embedder.embed_for_reindex(&memo.content).await?;
+        let source = memo.clone();
+        memo.embedding_status = EmbeddingStatus::Ready;
+        qdrant
+            .upsert_memo_into(target, &memo, vector, model)
+            .await?;
+        copied.insert(memo.id, source);
+        self.status.write().unwrap().processed += 1;
+        Ok(())
+    }
+}
+
+/// 張り替え前に失敗したとき、作りかけのコレクションを消す
+async fn discard_targets<T>(qdrant: &QdrantService, targets: &[(&str, String, T)]) {
+    for (_, target, _) in targets {
+        if let Err(e) = qdrant.drop_collection(target).await {
+            tracing::warn!("Failed to drop {}: {}", target, e);
+        }
+    }
+}
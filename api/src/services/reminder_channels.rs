@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tokio::sync::broadcast;
+
+use crate::error::{AppError, Result};
+use crate::models::{MemoDate, ReminderNotification};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// SSE の購読者ごとに溜められる未読の通知数
+const STREAM_CAPACITY: usize = 64;
+
+/// 1つのチャネルへの送信結果
+pub enum Delivery {
+    Delivered,
+    /// 受け取る相手がいない (SSE の購読者がいない)。失敗には数えない
+    NoRecipient,
+    Failed(String),
+}
+
+/// リマインダーの送り先。設定されたものすべてに送る
+pub enum ReminderChannel {
+    /// 通知の JSON を POST する
+    Webhook {
+        client: reqwest::Client,
+        url: String,
+    },
+    Email(Box<EmailChannel>),
+    /// `GET /reminders/stream` (SSE) の購読者に流す
+    Stream(broadcast::Sender<ReminderNotification>),
+}
+
+impl ReminderChannel {
+    pub fn webhook(url: String) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(Self::Webhook { client, url })
+    }
+
+    pub fn stream() -> Self {
+        Self::Stream(broadcast::channel(STREAM_CAPACITY).0)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Webhook { .. } => "webhook",
+            Self::Email(_) => "email",
+            Self::Stream(_) => "stream",
+        }
+    }
+
+    pub async fn deliver(&self, notification: &ReminderNotification) -> Delivery {
+        match self {
+            Self::Webhook { client, url } => {
+                let response = client.post(url).json(notification).send().await;
+                match response.and_then(|r| r.error_for_status()) {
+                    Ok(_) => Delivery::Delivered,
+                    Err(e) => Delivery::Failed(e.to_string()),
+                }
+            }
+            Self::Email(email) => match email.send(notification).await {
+                Ok(()) => Delivery::Delivered,
+                Err(e) => Delivery::Failed(e.to_string()),
+            },
+            Self::Stream(sender) => match sender.send(notification.clone()) {
+                Ok(_) => Delivery::Delivered,
+                Err(_) => Delivery::NoRecipient,
+            },
+        }
+    }
+}
+
+/// SMTP の接続方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpSecurity {
+    /// 平文で接続して STARTTLS に切り替える (587番)
+    StartTls,
+    /// 最初から TLS (465番)
+    Tls,
+    /// 暗号化しない (ローカルのリレー用)
+    None,
+}
+
+impl std::str::FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "starttls" => Ok(Self::StartTls),
+            "tls" => Ok(Self::Tls),
+            "none" => Ok(Self::None),
+            other => Err(format!("Unknown SMTP security: {}", other)),
+        }
+    }
+}
+
+pub struct SmtpOptions {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+pub struct EmailChannel {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Mailbox,
+}
+
+impl EmailChannel {
+    pub fn new(options: SmtpOptions) -> Result<Self> {
+        let smtp_error = |e: lettre::transport::smtp::Error| AppError::Internal(e.to_string());
+        let builder = match options.security {
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&options.host)
+                    .map_err(smtp_error)?
+            }
+            SmtpSecurity::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&options.host).map_err(smtp_error)?
+            }
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&options.host)
+            }
+        };
+        let builder = match (options.username, options.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username, password))
+            }
+            _ => builder,
+        };
+
+        let mailbox = |address: &str| {
+            address.parse::<Mailbox>().map_err(|e| {
+                AppError::Internal(format!("Invalid email address {}: {}", address, e))
+            })
+        };
+        Ok(Self {
+            transport: builder.port(options.port).build(),
+            from: mailbox(&options.from)?,
+            to: mailbox(&options.to)?,
+        })
+    }
+
+    async fn send(&self, notification: &ReminderNotification) -> Result<()> {
+        let title = notification.content.lines().next().unwrap_or_default();
+        let mut body = notification.content.clone();
+        if notification.from.is_some() || notification.until.is_some() {
+            let date = |d: Option<MemoDate>| d.map(|d| d.to_string()).unwrap_or_default();
+            body.push_str(&format!(
+                "\n\n{} 〜 {}",
+                date(notification.from),
+                date(notification.until)
+            ));
+            if let Some(timezone) = notification.timezone {
+                body.push_str(&format!(" ({})", timezone.name()));
+            }
+        }
+        if !notification.tags.is_empty() {
+            body.push_str(&format!("\n{}", notification.tags.join(", ")));
+        }
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(self.to.clone())
+            .subject(format!("リマインダー: {}", title))
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,642 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use qdrant_client::qdrant::{
+    vectors_config, Condition, CreateCollectionBuilder, DeletePointsBuilder, FieldType, Filter,
+    GetPointsBuilder, PointId, PointStruct, PointsIdsList, Range, ScrollPointsBuilder,
+    SetPayloadPointsBuilder, UpsertPointsBuilder, Value, VectorParamsMap, VectorsConfig,
+};
+use tokio::sync::{broadcast, Notify};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{Reminder, ReminderNotification, ReminderStatus};
+use crate::services::reminder_channels::{Delivery, ReminderChannel};
+use crate::services::QdrantService;
+
+const COLLECTION_REMINDERS: &str = "reminders";
+/// 次のリマインダーが無くても、この間隔でQdrantを見直す
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// 送信に失敗したときの再試行間隔 (2倍ずつ延ばす)
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 5;
+/// 過去の時刻を指定できる許容幅 (クライアントとの時計のずれ)
+const PAST_TOLERANCE: TimeDelta = TimeDelta::minutes(1);
+
+/// 全チャネルに受け手がいなかった (SSE の購読者がいない) リマインダーの印。
+/// 購読が始まったら外して送り直す
+const AWAITING_LISTENER: &str = "awaiting_listener";
+/// 次に送る時刻 (UNIX秒)。期限が来たものだけを範囲検索で取り出すために持つ
+const NEXT_ATTEMPT_TS: &str = "next_attempt_ts";
+
+/// ワーカーが毎回の見直しで絞り込むフィールド
+const PAYLOAD_INDEXES: [(&str, FieldType); 4] = [
+    ("memo_id", FieldType::Keyword),
+    ("status", FieldType::Keyword),
+    (AWAITING_LISTENER, FieldType::Bool),
+    (NEXT_ATTEMPT_TS, FieldType::Integer),
+];
+
+/// リマインダーを `reminders` コレクションに保存し、時刻になったら各チャネルに送るワーカー。
+/// 状態はQdrantに永続化しているので、停止中に時刻を過ぎたものは再起動後に送る
+#[derive(Clone)]
+pub struct ReminderScheduler {
+    qdrant: QdrantService,
+    channels: Arc<Vec<ReminderChannel>>,
+    notify: Arc<Notify>,
+}
+
+impl ReminderScheduler {
+    /// `channels` に加えて SSE のチャネルを必ず持つ
+    pub fn new(qdrant: QdrantService, mut channels: Vec<ReminderChannel>) -> Self {
+        channels.push(ReminderChannel::stream());
+        Self {
+            qdrant,
+            channels: Arc::new(channels),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// リマインダーはベクトルを持たない。payloadインデックスは既存のコレクションにも追加する
+    pub async fn ensure_collection(&self) -> Result<()> {
+        let exists = self
+            .qdrant
+            .client()
+            .collection_exists(COLLECTION_REMINDERS)
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        if !exists {
+            self.qdrant
+                .client()
+                .create_collection(
+                    CreateCollectionBuilder::new(COLLECTION_REMINDERS).vectors_config(
+                        VectorsConfig {
+                            config: Some(vectors_config::Config::ParamsMap(VectorParamsMap {
+                                map: HashMap::new(),
+                            })),
+                        },
+                    ),
+                )
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+            tracing::info!("Created collection: {}", COLLECTION_REMINDERS);
+        }
+
+        self.qdrant
+            .create_payload_indexes(COLLECTION_REMINDERS, &PAYLOAD_INDEXES)
+            .await
+    }
+
+    pub fn start(&self) {
+        let names: Vec<&str> = self.channels.iter().map(|c| c.name()).collect();
+        tracing::info!("Reminder channels: {}", names.join(", "));
+
+        let worker = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let wait = match worker.fire_due().await {
+                    Ok(Some(next)) => (next - Utc::now())
+                        .to_std()
+                        .unwrap_or_default()
+                        .min(POLL_INTERVAL),
+                    Ok(None) => POLL_INTERVAL,
+                    Err(e) => {
+                        tracing::warn!("Reminder worker failed: {}", e);
+                        RETRY_INTERVAL
+                    }
+                };
+
+                tokio::select! {
+                    _ = worker.notify.notified() => {}
+                    _ = tokio::time::sleep(wait) => {}
+                }
+            }
+        });
+    }
+
+    /// SSE の購読を始める。購読者がいなくて送れなかったリマインダーを送り直す
+    pub async fn subscribe(&self) -> Result<broadcast::Receiver<ReminderNotification>> {
+        let receiver = self
+            .channels
+            .iter()
+            .find_map(|channel| match channel {
+                ReminderChannel::Stream(sender) => Some(sender.subscribe()),
+                _ => None,
+            })
+            .expect("the stream channel is always registered");
+
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert(AWAITING_LISTENER.into(), false.into());
+        self.qdrant
+            .client()
+            .set_payload(
+                SetPayloadPointsBuilder::new(COLLECTION_REMINDERS, payload)
+                    .points_selector(Filter::must([
+                        Condition::matches("status", ReminderStatus::Pending.as_str().to_string()),
+                        Condition::matches(AWAITING_LISTENER, true),
+                    ]))
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        self.notify.notify_one();
+
+        Ok(receiver)
+    }
+
+    pub async fn create(&self, memo_id: Uuid, remind_at: DateTime<Utc>) -> Result<Reminder> {
+        let now = Utc::now();
+        if remind_at < now - PAST_TOLERANCE {
+            return Err(AppError::BadRequest("remind_at is in the past".into()));
+        }
+
+        let reminder = Reminder {
+            id: Uuid::new_v4(),
+            memo_id,
+            remind_at,
+            status: ReminderStatus::Pending,
+            created_at: now,
+            sent_at: None,
+            attempts: 0,
+            last_error: None,
+        };
+        self.save(&reminder, remind_at, false).await?;
+        self.notify.notify_one();
+        Ok(reminder)
+    }
+
+    /// 時刻順。`memo_id` / `status` で絞り込める
+    pub async fn list(
+        &self,
+        memo_id: Option<Uuid>,
+        status: Option<ReminderStatus>,
+    ) -> Result<Vec<Reminder>> {
+        let mut conditions = Vec::new();
+        if let Some(memo_id) = memo_id {
+            conditions.push(Condition::matches("memo_id", memo_id.to_string()));
+        }
+        if let Some(status) = status {
+            conditions.push(Condition::matches("status", status.as_str().to_string()));
+        }
+        let filter = (!conditions.is_empty()).then(|| Filter::must(conditions));
+
+        let mut reminders: Vec<Reminder> = self
+            .scroll(filter)
+            .await?
+            .into_iter()
+            .map(|(reminder, _)| reminder)
+            .collect();
+        reminders.sort_by_key(|r| (r.remind_at, r.created_at));
+        Ok(reminders)
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let point_id: PointId = id.to_string().into();
+        let existing = self
+            .qdrant
+            .client()
+            .get_points(GetPointsBuilder::new(
+                COLLECTION_REMINDERS,
+                vec![point_id.clone()],
+            ))
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        if existing.result.is_empty() {
+            return Err(AppError::NotFound(format!("Reminder {} not found", id)));
+        }
+
+        self.qdrant
+            .client()
+            .delete_points(
+                DeletePointsBuilder::new(COLLECTION_REMINDERS)
+                    .points(PointsIdsList {
+                        ids: vec![point_id],
+                    })
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        Ok(())
+    }
+
+    /// メモを削除したときに、そのメモのリマインダーをまとめて削除する
+    pub async fn delete_for_memo(&self, memo_id: Uuid) -> Result<()> {
+        self.qdrant
+            .client()
+            .delete_points(
+                DeletePointsBuilder::new(COLLECTION_REMINDERS)
+                    .points(Filter::must([Condition::matches(
+                        "memo_id",
+                        memo_id.to_string(),
+                    )]))
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        Ok(())
+    }
+
+    /// メモを統合したときに、元のメモのリマインダーを統合後のメモに付け替える
+    pub async fn move_to_memo(&self, from: Uuid, to: Uuid) -> Result<()> {
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("memo_id".into(), to.to_string().into());
+        self.qdrant
+            .client()
+            .set_payload(
+                SetPayloadPointsBuilder::new(COLLECTION_REMINDERS, payload)
+                    .points_selector(Filter::must([Condition::matches(
+                        "memo_id",
+                        from.to_string(),
+                    )]))
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 時刻を過ぎたリマインダーを送る。まだ時刻になっていない最も早いリマインダーの時刻を返す。
+    /// 1件の送信処理が失敗しても残りは送る
+    async fn fire_due(&self) -> Result<Option<DateTime<Utc>>> {
+        let now = Utc::now();
+        let due = Filter {
+            must: vec![
+                Condition::matches("status", ReminderStatus::Pending.as_str().to_string()),
+                // next_attempt_ts 導入前に保存したものは時刻を読んでから判定する
+                Filter::should([
+                    Condition::range(
+                        NEXT_ATTEMPT_TS,
+                        Range {
+                            lte: Some(now.timestamp() as f64),
+                            ..Default::default()
+                        },
+                    ),
+                    Condition::is_empty(NEXT_ATTEMPT_TS),
+                ])
+                .into(),
+            ],
+            must_not: vec![Condition::matches(AWAITING_LISTENER, true)],
+            ..Default::default()
+        };
+
+        let mut failed = false;
+        for (reminder, next_attempt) in self.scroll(Some(due)).await? {
+            let result = if next_attempt > now {
+                self.save(&reminder, next_attempt, false).await
+            } else {
+                self.fire(reminder.clone()).await
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to process reminder {}: {}", reminder.id, e);
+                failed = true;
+            }
+        }
+
+        let next = self.next_attempt(now).await?;
+        if failed {
+            let retry = now + TimeDelta::from_std(RETRY_INTERVAL).unwrap_or_default();
+            return Ok(Some(next.map_or(retry, |n| n.min(retry))));
+        }
+        Ok(next)
+    }
+
+    /// まだ時刻になっていない送信待ちのうち、最も早い時刻
+    async fn next_attempt(&self, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+        let filter = Filter {
+            must: vec![
+                Condition::matches("status", ReminderStatus::Pending.as_str().to_string()),
+                Condition::range(
+                    NEXT_ATTEMPT_TS,
+                    Range {
+                        gt: Some(now.timestamp() as f64),
+                        ..Default::default()
+                    },
+                ),
+            ],
+            must_not: vec![Condition::matches(AWAITING_LISTENER, true)],
+            ..Default::default()
+        };
+
+        let result = self
+            .qdrant
+            .client()
+            .scroll(
+                ScrollPointsBuilder::new(COLLECTION_REMINDERS)
+                    .filter(filter)
+                    .order_by(NEXT_ATTEMPT_TS)
+                    .with_payload(true)
+                    .limit(1),
+            )
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        result
+            .result
+            .first()
+            .map(|point| {
+                self.qdrant
+                    .get_datetime_field(&point.payload, "next_attempt")
+            })
+            .transpose()
+    }
+
+    async fn fire(&self, mut reminder: Reminder) -> Result<()> {
+        let memo = self.qdrant.get_memo(reminder.memo_id, false).await?;
+        let Some(memo) = memo.filter(|m| !m.completed) else {
+            tracing::info!(
+                "Cancelled reminder {}: memo {} is gone or completed",
+                reminder.id,
+                reminder.memo_id
+            );
+            reminder.status = ReminderStatus::Cancelled;
+            return self.save(&reminder, reminder.remind_at, false).await;
+        };
+
+        let notification = ReminderNotification {
+            reminder_id: reminder.id,
+            memo_id: memo.id,
+            remind_at: reminder.remind_at,
+            content: memo.content,
+            tags: memo.tags,
+            from: memo.from,
+            until: memo.until,
+            timezone: memo.timezone,
+        };
+
+        let mut deliveries = Vec::with_capacity(self.channels.len());
+        for channel in self.channels.iter() {
+            let delivery = channel.deliver(&notification).await;
+            if let Delivery::Failed(ref e) = delivery {
+                tracing::warn!(
+                    "Failed to deliver reminder {} via {}: {}",
+                    reminder.id,
+                    channel.name(),
+                    e
+                );
+            }
+            deliveries.push((channel.name(), delivery));
+        }
+
+        let (next_attempt, awaiting_listener) =
+            record_deliveries(&mut reminder, deliveries, Utc::now());
+        match reminder.status {
+            ReminderStatus::Sent => {
+                tracing::info!("Sent reminder {} for memo {}", reminder.id, memo.id)
+            }
+            ReminderStatus::Failed => tracing::error!(
+                "Giving up reminder {} after {} attempts",
+                reminder.id,
+                reminder.attempts
+            ),
+            _ => {}
+        }
+        self.save(&reminder, next_attempt, awaiting_listener).await
+    }
+
+    async fn save(
+        &self,
+        reminder: &Reminder,
+        next_attempt: DateTime<Utc>,
+        awaiting_listener: bool,
+    ) -> Result<()> {
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("memo_id".into(), reminder.memo_id.to_string().into());
+        payload.insert("remind_at".into(), reminder.remind_at.to_rfc3339().into());
+        payload.insert("status".into(), reminder.status.as_str().into());
+        payload.insert("created_at".into(), reminder.created_at.to_rfc3339().into());
+        payload.insert("attempts".into(), (reminder.attempts as i64).into());
+        payload.insert("next_attempt".into(), next_attempt.to_rfc3339().into());
+        payload.insert(NEXT_ATTEMPT_TS.into(), next_attempt.timestamp().into());
+        payload.insert(AWAITING_LISTENER.into(), awaiting_listener.into());
+        if let Some(sent_at) = reminder.sent_at {
+            payload.insert("sent_at".into(), sent_at.to_rfc3339().into());
+        }
+        if let Some(ref error) = reminder.last_error {
+            payload.insert("last_error".into(), error.clone().into());
+        }
+
+        let point = PointStruct::new(
+            reminder.id.to_string(),
+            HashMap::<String, Vec<f32>>::new(),
+            payload,
+        );
+        self.qdrant
+            .client()
+            .upsert_points(UpsertPointsBuilder::new(COLLECTION_REMINDERS, vec![point]).wait(true))
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        Ok(())
+    }
+
+    /// リマインダーと次に送る時刻
+    async fn scroll(&self, filter: Option<Filter>) -> Result<Vec<(Reminder, DateTime<Utc>)>> {
+        let mut reminders = Vec::new();
+        let mut offset: Option<PointId> = None;
+        let page_size = 100u32;
+
+        loop {
+            let mut scroll_builder = ScrollPointsBuilder::new(COLLECTION_REMINDERS)
+                .with_payload(true)
+                .limit(page_size);
+
+            if let Some(ref f) = filter {
+                scroll_builder = scroll_builder.filter(f.clone());
+            }
+            if let Some(ref off) = offset {
+                scroll_builder = scroll_builder.offset(off.clone());
+            }
+
+            let result = self
+                .qdrant
+                .client()
+                .scroll(scroll_builder)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+            for point in &result.result {
+                let id = self.qdrant.extract_uuid_from_point_id(&point.id)?;
+                reminders.push(self.payload_to_reminder(id, &point.payload)?);
+            }
+
+            match result.next_page_offset {
+                Some(next_offset) => offset = Some(next_offset),
+                None => break,
+            }
+        }
+
+        Ok(reminders)
+    }
+
+    fn payload_to_reminder(
+        &self,
+        id: Uuid,
+        payload: &HashMap<String, Value>,
+    ) -> Result<(Reminder, DateTime<Utc>)> {
+        let memo_id = self.qdrant.get_string_field(payload, "memo_id")?;
+        let memo_id = Uuid::parse_str(&memo_id)
+            .map_err(|e| AppError::Qdrant(format!("Invalid memo_id: {}", e)))?;
+        let status = match self.qdrant.get_string_field(payload, "status")?.as_str() {
+            "pending" => ReminderStatus::Pending,
+            "sent" => ReminderStatus::Sent,
+            "failed" => ReminderStatus::Failed,
+            "cancelled" => ReminderStatus::Cancelled,
+            other => {
+                return Err(AppError::Qdrant(format!(
+                    "Unknown reminder status: {}",
+                    other
+                )))
+            }
+        };
+        let remind_at = self.qdrant.get_datetime_field(payload, "remind_at")?;
+
+        let reminder = Reminder {
+            id,
+            memo_id,
+            remind_at,
+            status,
+            created_at: self.qdrant.get_datetime_field(payload, "created_at")?,
+            sent_at: self.qdrant.get_datetime_field(payload, "sent_at").ok(),
+            attempts: self.qdrant.get_int_field(payload, "attempts").unwrap_or(0) as u32,
+            last_error: self.qdrant.get_string_field(payload, "last_error").ok(),
+        };
+        let next_attempt = self
+            .qdrant
+            .get_datetime_field(payload, "next_attempt")
+            .unwrap_or(remind_at);
+        Ok((reminder, next_attempt))
+    }
+}
+
+/// チャネルごとの送信結果をリマインダーに反映する。次に送る時刻と、購読者待ちにするかを返す
+fn record_deliveries(
+    reminder: &mut Reminder,
+    deliveries: Vec<(&str, Delivery)>,
+    now: DateTime<Utc>,
+) -> (DateTime<Utc>, bool) {
+    let mut delivered = false;
+    let mut errors = Vec::new();
+    for (channel, delivery) in deliveries {
+        match delivery {
+            Delivery::Delivered => delivered = true,
+            Delivery::NoRecipient => {}
+            Delivery::Failed(e) => errors.push(format!("{}: {}", channel, e)),
+        }
+    }
+    reminder.last_error = (!errors.is_empty()).then(|| errors.join("; "));
+
+    // 1つでも届けば送信済み。失敗したチャネルには送り直さない
+    if delivered {
+        reminder.status = ReminderStatus::Sent;
+        reminder.sent_at = Some(now);
+        return (reminder.remind_at, false);
+    }
+    if errors.is_empty() {
+        return (reminder.remind_at, true);
+    }
+
+    reminder.attempts += 1;
+    if reminder.attempts >= MAX_ATTEMPTS {
+        reminder.status = ReminderStatus::Failed;
+        return (reminder.remind_at, false);
+    }
+    let delay = RETRY_BASE_DELAY * 2u32.pow(reminder.attempts - 1);
+    (
+        now + TimeDelta::from_std(delay).unwrap_or(TimeDelta::MAX),
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending() -> Reminder {
+        let remind_at = DateTime::parse_from_rfc3339("2026-01-15T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        Reminder {
+            id: Uuid::new_v4(),
+            memo_id: Uuid::new_v4(),
+            remind_at,
+            status: ReminderStatus::Pending,
+            created_at: remind_at,
+            sent_at: None,
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    fn failed(message: &str) -> Delivery {
+        Delivery::Failed(message.to_string())
+    }
+
+    #[test]
+    fn one_delivery_marks_sent() {
+        let mut reminder = pending();
+        let now = reminder.remind_at + TimeDelta::seconds(1);
+        let deliveries = vec![("webhook", failed("500")), ("stream", Delivery::Delivered)];
+
+        let (next, awaiting) = record_deliveries(&mut reminder, deliveries, now);
+        assert_eq!(reminder.status, ReminderStatus::Sent);
+        assert_eq!(reminder.sent_at, Some(now));
+        assert_eq!(reminder.attempts, 0);
+        assert_eq!(reminder.last_error.as_deref(), Some("webhook: 500"));
+        assert_eq!(next, reminder.remind_at);
+        assert!(!awaiting);
+    }
+
+    #[test]
+    fn no_recipient_waits_for_listener() {
+        let mut reminder = pending();
+        let now = reminder.remind_at;
+
+        let (next, awaiting) =
+            record_deliveries(&mut reminder, vec![("stream", Delivery::NoRecipient)], now);
+        assert_eq!(reminder.status, ReminderStatus::Pending);
+        assert_eq!(reminder.attempts, 0);
+        assert_eq!(reminder.last_error, None);
+        assert_eq!(next, reminder.remind_at);
+        assert!(awaiting);
+    }
+
+    #[test]
+    fn failures_back_off_exponentially() {
+        let mut reminder = pending();
+        let now = reminder.remind_at;
+        let deliveries = || {
+            vec![
+                ("webhook", failed("timeout")),
+                ("stream", Delivery::NoRecipient),
+            ]
+        };
+
+        let (next, awaiting) = record_deliveries(&mut reminder, deliveries(), now);
+        assert_eq!(reminder.status, ReminderStatus::Pending);
+        assert_eq!(reminder.attempts, 1);
+        assert_eq!(next, now + TimeDelta::seconds(30));
+        assert!(!awaiting);
+
+        let (next, _) = record_deliveries(&mut reminder, deliveries(), now);
+        assert_eq!(reminder.attempts, 2);
+        assert_eq!(next, now + TimeDelta::seconds(60));
+        assert_eq!(reminder.last_error.as_deref(), Some("webhook: timeout"));
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut reminder = pending();
+        reminder.attempts = MAX_ATTEMPTS - 1;
+        let now = reminder.remind_at + TimeDelta::hours(1);
+
+        let (next, awaiting) =
+            record_deliveries(&mut reminder, vec![("email", failed("refused"))], now);
+        assert_eq!(reminder.status, ReminderStatus::Failed);
+        assert_eq!(reminder.attempts, MAX_ATTEMPTS);
+        assert_eq!(reminder.sent_at, None);
+        assert_eq!(next, reminder.remind_at);
+        assert!(!awaiting);
+    }
+}
@@ -0,0 +1,283 @@
+use chrono::{Days, NaiveDate};
+
+use crate::error::{AppError, Result};
+use crate::models::{MemoType, QueryInterpretation, SearchFilters};
+
+/// 検索クエリの演算子 (`tag:健康 type:flash after:2026-01-01 -is:completed -done "完全一致"`) を
+/// フィルタに分解する。演算子でない部分は自由文として残し、それだけをベクトル化する
+pub fn parse_query(query: &str) -> Result<QueryInterpretation> {
+    let mut parsed = QueryInterpretation::default();
+    let mut text = Vec::new();
+    let mut errors = Vec::new();
+
+    for token in tokenize(query) {
+        match token {
+            Token::Phrase(phrase) => {
+                if !phrase.trim().is_empty() {
+                    parsed.phrases.push(phrase);
+                }
+            }
+            Token::Word(word) => {
+                if let Err(e) = apply_operator(&mut parsed, &word) {
+                    match e {
+                        OperatorError::NotAnOperator => text.push(word),
+                        OperatorError::Invalid(message) => errors.push(message),
+                    }
+                }
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(AppError::BadRequest(format!(
+            "Invalid search query: {}",
+            errors.join("; ")
+        )));
+    }
+
+    parsed.text = text.join(" ");
+    Ok(parsed)
+}
+
+impl QueryInterpretation {
+    /// リクエストで明示されたフィルタに、クエリから読み取った条件を重ねる
+    pub fn apply_to(&self, filters: &mut SearchFilters) {
+        for tag in &self.tags {
+            if !filters.tags.contains(tag) {
+                filters.tags.push(tag.clone());
+            }
+        }
+        filters.phrases.extend(self.phrases.iter().cloned());
+        if self.memo_type.is_some() {
+            filters.memo_type = self.memo_type.clone();
+        }
+        if self.from_gte.is_some() {
+            filters.from_gte = self.from_gte;
+        }
+        if self.until_lte.is_some() {
+            filters.until_lte = self.until_lte;
+        }
+        if self.completed.is_some() {
+            filters.completed = self.completed;
+        }
+    }
+}
+
+enum Token {
+    Word(String),
+    Phrase(String),
+}
+
+enum OperatorError {
+    /// `key:value` の形でないか、知らないキー。自由文として扱う
+    NotAnOperator,
+    Invalid(String),
+}
+
+/// 空白区切りで分ける。`"..."` (全角の `“...”` も) は空白を含めて1つのフレーズにし、
+/// `tag:"a b"` のように値の中の引用符も空白をまたげる
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if let Some(close) = closing_quote(c) {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != close).collect();
+            tokens.push(Token::Phrase(phrase));
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            match closing_quote(c) {
+                Some(close) => word.extend(chars.by_ref().take_while(|&c| c != close)),
+                None => word.push(c),
+            }
+        }
+        tokens.push(Token::Word(word));
+    }
+
+    tokens
+}
+
+fn closing_quote(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '“' => Some('”'),
+        _ => None,
+    }
+}
+
+fn apply_operator(
+    parsed: &mut QueryInterpretation,
+    word: &str,
+) -> std::result::Result<(), OperatorError> {
+    let (negated, body) = match word.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, word),
+    };
+    // 単独の `done` / `-done` は is:done と同じ
+    if body.eq_ignore_ascii_case("done") {
+        parsed.completed = Some(!negated);
+        return Ok(());
+    }
+    let Some((key, value)) = body.split_once([':', '：']) else {
+        return Err(OperatorError::NotAnOperator);
+    };
+    let key = key.to_lowercase();
+    if !OPERATORS.contains(&key.as_str()) {
+        return Err(OperatorError::NotAnOperator);
+    }
+    if value.is_empty() {
+        return Err(OperatorError::Invalid(format!("{}: missing value", word)));
+    }
+    if negated && key != "is" {
+        return Err(OperatorError::Invalid(format!(
+            "{}: only is: can be negated",
+            word
+        )));
+    }
+
+    match key.as_str() {
+        "tag" => parsed.tags.push(value.to_string()),
+        "type" => {
+            parsed.memo_type = Some(match value.to_lowercase().as_str() {
+                "flash" => MemoType::Flash,
+                "permanent" => MemoType::Permanent,
+                _ => {
+                    return Err(OperatorError::Invalid(format!(
+                        "{}: type must be flash or permanent",
+                        word
+                    )))
+                }
+            })
+        }
+        "is" => match value.to_lowercase().as_str() {
+            "completed" | "done" => parsed.completed = Some(!negated),
+            _ => {
+                return Err(OperatorError::Invalid(format!(
+                    "{}: unknown state (expected is:completed or is:done)",
+                    word
+                )))
+            }
+        },
+        // from:/until: は指定日を含み、after:/before: は含まない
+        "from" | "after" => {
+            let date = parse_date(word, value)?;
+            let date = if key == "after" {
+                date.checked_add_days(Days::new(1))
+                    .ok_or_else(|| out_of_range(word))?
+            } else {
+                date
+            };
+            parsed.from_gte = Some(parsed.from_gte.map_or(date, |d| d.max(date)));
+        }
+        "until" | "before" => {
+            let date = parse_date(word, value)?;
+            let date = if key == "before" {
+                date.checked_sub_days(Days::new(1))
+                    .ok_or_else(|| out_of_range(word))?
+            } else {
+                date
+            };
+            parsed.until_lte = Some(parsed.until_lte.map_or(date, |d| d.min(date)));
+        }
+        _ => unreachable!("checked against OPERATORS"),
+    }
+
+    Ok(())
+}
+
+const OPERATORS: &[&str] = &["tag", "type", "is", "from", "until", "after", "before"];
+
+fn parse_date(word: &str, value: &str) -> std::result::Result<NaiveDate, OperatorError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        OperatorError::Invalid(format!("{}: dates must be written as YYYY-MM-DD", word))
+    })
+}
+
+fn out_of_range(word: &str) -> OperatorError {
+    OperatorError::Invalid(format!("{}: date out of range", word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn splits_operators_from_free_text() {
+        let parsed =
+            parse_query("歯医者 tag:健康 type:Flash after:2026-01-01 予約 \"午前中\"").unwrap();
+        assert_eq!(parsed.text, "歯医者 予約");
+        assert_eq!(parsed.tags, vec!["健康"]);
+        assert_eq!(parsed.memo_type, Some(MemoType::Flash));
+        assert_eq!(parsed.from_gte, Some(date(2026, 1, 2)));
+        assert_eq!(parsed.phrases, vec!["午前中"]);
+        assert_eq!(parsed.completed, None);
+    }
+
+    #[test]
+    fn completed_operators() {
+        for (query, completed) in [
+            ("is:completed", true),
+            ("-is:completed", false),
+            ("is:done", true),
+            ("-is:done", false),
+            ("IS:Done", true),
+            ("done", true),
+            ("-done", false),
+            ("DONE", true),
+        ] {
+            let parsed = parse_query(query).unwrap();
+            assert_eq!(parsed.completed, Some(completed), "{}", query);
+            assert_eq!(parsed.text, "", "{}", query);
+        }
+        // 語の一部なら自由文
+        assert_eq!(parse_query("undone").unwrap().completed, None);
+        assert!(parse_query("is:open").is_err());
+    }
+
+    #[test]
+    fn date_bounds_keep_the_narrowest_range() {
+        let parsed =
+            parse_query("from:2026-01-01 after:2026-01-05 until:2026-02-01 before:2026-01-20")
+                .unwrap();
+        assert_eq!(parsed.from_gte, Some(date(2026, 1, 6)));
+        assert_eq!(parsed.until_lte, Some(date(2026, 1, 19)));
+    }
+
+    #[test]
+    fn quoted_values_and_fullwidth_colons() {
+        let parsed = parse_query("tag:\"a b\" tag：仕事 “全角 引用”").unwrap();
+        assert_eq!(parsed.tags, vec!["a b", "仕事"]);
+        assert_eq!(parsed.phrases, vec!["全角 引用"]);
+        assert_eq!(parsed.text, "");
+    }
+
+    #[test]
+    fn unknown_keys_stay_in_the_text() {
+        let parsed = parse_query("https://example.com note:1").unwrap();
+        assert_eq!(parsed.text, "https://example.com note:1");
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for query in ["type:foo", "from:2026/01/01", "tag:", "-tag:健康"] {
+            assert!(parse_query(query).is_err(), "{} should be rejected", query);
+        }
+    }
+}
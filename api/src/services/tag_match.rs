@@ -0,0 +1,199 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// タグ照合用のキー。NFKC (全角英数・半角カナの統一) → 小文字化 → カタカナをひらがなに寄せる
+pub fn match_key(text: &str) -> String {
+    text.nfkc()
+        .flat_map(char::to_lowercase)
+        .map(katakana_to_hiragana)
+        .collect()
+}
+
+fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// ローマ字入力をひらがなに変換する。ASCII英字以外を含む場合や変換できない場合は None。
+/// 入力途中の子音 (`kenk` の `k`) は捨てて、確定した部分だけを返す
+pub fn romaji_to_hiragana(text: &str) -> Option<String> {
+    let input = text.to_ascii_lowercase();
+    if input.is_empty() || !input.bytes().all(|b| b.is_ascii_lowercase() || b == b'-') {
+        return None;
+    }
+
+    let bytes = input.as_bytes();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let rest = &input[i..];
+
+        // 促音: 同じ子音の連続 (kk, tt, tch など)。長音記号の連続 (`--`) は「ーー」のまま
+        if bytes.len() > i + 1
+            && bytes[i] == bytes[i + 1]
+            && !is_vowel(bytes[i])
+            && bytes[i] != b'n'
+            && bytes[i] != b'-'
+        {
+            out.push('っ');
+            i += 1;
+            continue;
+        }
+        if rest.starts_with("tch") {
+            out.push('っ');
+            i += 1;
+            continue;
+        }
+
+        // 撥音: n の後が母音・y 以外なら「ん」
+        if bytes[i] == b'n' {
+            match bytes.get(i + 1) {
+                Some(b'n') => {
+                    out.push('ん');
+                    i += 2;
+                    continue;
+                }
+                Some(&next) if !is_vowel(next) && next != b'y' => {
+                    out.push('ん');
+                    i += 1;
+                    continue;
+                }
+                // 末尾の n は「な」行の入力途中かもしれないので確定させない
+                None => break,
+                _ => {}
+            }
+        }
+
+        let matched = (1..=3).rev().find_map(|len| {
+            rest.get(..len)
+                .and_then(|key| ROMAJI.iter().find(|(romaji, _)| *romaji == key))
+                .map(|(_, kana)| (len, *kana))
+        });
+
+        match matched {
+            Some((len, kana)) => {
+                out.push_str(kana);
+                i += len;
+            }
+            // 残りが子音だけなら入力途中とみなす
+            None if rest.bytes().all(|b| !is_vowel(b)) => break,
+            None => return None,
+        }
+    }
+
+    (!out.is_empty()).then_some(out)
+}
+
+fn is_vowel(b: u8) -> bool {
+    matches!(b, b'a' | b'i' | b'u' | b'e' | b'o')
+}
+
+#[rustfmt::skip]
+const ROMAJI: &[(&str, &str)] = &[
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("sa", "さ"), ("si", "し"), ("shi", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("za", "ざ"), ("zi", "じ"), ("ji", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("ta", "た"), ("ti", "ち"), ("chi", "ち"), ("tu", "つ"), ("tsu", "つ"), ("te", "て"), ("to", "と"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("de", "で"), ("do", "ど"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("ha", "は"), ("hi", "ひ"), ("hu", "ふ"), ("fu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("ya", "や"), ("yu", "ゆ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("wa", "わ"), ("wo", "を"),
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kyo", "きょ"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gyo", "ぎょ"),
+    ("sha", "しゃ"), ("shu", "しゅ"), ("sho", "しょ"), ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+    ("ja", "じゃ"), ("ju", "じゅ"), ("jo", "じょ"), ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"),
+    ("cha", "ちゃ"), ("chu", "ちゅ"), ("cho", "ちょ"), ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nyo", "にょ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hyo", "ひょ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("byo", "びょ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pyo", "ぴょ"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("myo", "みょ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("ryo", "りょ"),
+    ("-", "ー"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hiragana(romaji: &str) -> Option<String> {
+        romaji_to_hiragana(romaji)
+    }
+
+    #[test]
+    fn match_key_unifies_width_case_and_kana() {
+        assert_eq!(match_key("ケンコウ"), "けんこう");
+        assert_eq!(match_key("ｹﾝｺｳ"), "けんこう");
+        assert_eq!(match_key("ＡＢＣ"), "abc");
+        assert_eq!(match_key("Health/歯医者"), "health/歯医者");
+        // 長音記号はカタカナの範囲外なのでそのまま
+        assert_eq!(match_key("メール"), "めーる");
+    }
+
+    #[test]
+    fn converts_basic_romaji() {
+        assert_eq!(hiragana("kenkou").as_deref(), Some("けんこう"));
+        assert_eq!(hiragana("shigoto").as_deref(), Some("しごと"));
+        assert_eq!(hiragana("tsukue").as_deref(), Some("つくえ"));
+        assert_eq!(hiragana("KAIGI").as_deref(), Some("かいぎ"));
+    }
+
+    #[test]
+    fn converts_contracted_sounds() {
+        assert_eq!(hiragana("kyou").as_deref(), Some("きょう"));
+        assert_eq!(hiragana("shashinn").as_deref(), Some("しゃしん"));
+        assert_eq!(hiragana("jyugyou").as_deref(), Some("じゅぎょう"));
+    }
+
+    #[test]
+    fn converts_sokuon() {
+        assert_eq!(hiragana("kitte").as_deref(), Some("きって"));
+        assert_eq!(hiragana("matcha").as_deref(), Some("まっちゃ"));
+        assert_eq!(hiragana("zasshi").as_deref(), Some("ざっし"));
+    }
+
+    #[test]
+    fn converts_n() {
+        // nn は常に「ん」(IME と同じく、「んに」は nnni)
+        assert_eq!(hiragana("konnnichiha").as_deref(), Some("こんにちは"));
+        assert_eq!(hiragana("konnichiha").as_deref(), Some("こんいちは"));
+        assert_eq!(hiragana("kanji").as_deref(), Some("かんじ"));
+        assert_eq!(hiragana("kinyou").as_deref(), Some("きにょう"));
+        // 末尾の n は確定させない (な行の入力途中かもしれない)
+        assert_eq!(hiragana("hon").as_deref(), Some("ほ"));
+        assert_eq!(hiragana("honn").as_deref(), Some("ほん"));
+    }
+
+    #[test]
+    fn converts_long_vowel_marks() {
+        assert_eq!(hiragana("me-ru").as_deref(), Some("めーる"));
+        // 連続しても促音にはしない
+        assert_eq!(hiragana("a--").as_deref(), Some("あーー"));
+    }
+
+    #[test]
+    fn drops_trailing_consonants_being_typed() {
+        assert_eq!(hiragana("kenk").as_deref(), Some("けん"));
+        assert_eq!(hiragana("shig").as_deref(), Some("し"));
+        assert_eq!(hiragana("ky"), None);
+    }
+
+    #[test]
+    fn rejects_non_romaji() {
+        assert_eq!(hiragana(""), None);
+        assert_eq!(hiragana("けんこう"), None);
+        assert_eq!(hiragana("abc1"), None);
+        assert_eq!(hiragana("work/meeting"), None);
+        // 表に無い綴り
+        assert_eq!(hiragana("qa"), None);
+    }
+}
@@ -0,0 +1,224 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::{AppError, Result};
+
+/// タグの表記ゆれを書き込み時に統一する。
+/// `健康/ 歯医者`・`健康//歯医者`・`Ｈｅａｌｔｈ` などを同じノードにまとめる
+#[derive(Debug, Clone)]
+pub struct TagNormalizer {
+    /// 英字を小文字に揃えるか
+    pub case_fold: bool,
+    /// 階層の深さの上限 (`a/b/c` は 3)
+    pub max_depth: usize,
+    /// パス全体の文字数の上限
+    pub max_length: usize,
+}
+
+impl TagNormalizer {
+    /// 各セグメントの前後の空白を除き、空のセグメントを詰め、NFKC正規化と (設定により) 小文字化を行う。
+    /// 空になったタグは None
+    pub fn canonicalize(&self, tag: &str) -> Option<String> {
+        let normalized: String = tag.nfkc().collect();
+        let normalized = if self.case_fold {
+            normalized.to_lowercase()
+        } else {
+            normalized
+        };
+
+        let path = normalized
+            .split('/')
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        (!path.is_empty()).then_some(path)
+    }
+
+    /// 正規化済みのタグが深さ・長さの制限を満たさなければ理由を返す
+    fn violation(&self, tag: &str) -> Option<String> {
+        let depth = tag.split('/').count();
+        if depth > self.max_depth {
+            return Some(format!(
+                "{}: depth {} exceeds the maximum of {}",
+                tag, depth, self.max_depth
+            ));
+        }
+
+        let length = tag.chars().count();
+        if length > self.max_length {
+            return Some(format!(
+                "{}: length {} exceeds the maximum of {} characters",
+                tag, length, self.max_length
+            ));
+        }
+
+        None
+    }
+
+    /// 書き込み前のタグ一覧を正規化する。空のタグは捨て、正規化後に重複したものはまとめる。
+    /// 制限違反があれば違反をすべて列挙した `BadRequest` を返す
+    pub fn normalize(&self, tags: &[String]) -> Result<Vec<String>> {
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+        let mut violations = Vec::new();
+
+        for tag in tags.iter().filter_map(|t| self.canonicalize(t)) {
+            if let Some(violation) = self.violation(&tag) {
+                violations.push(violation);
+            } else if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+
+        if !violations.is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "Invalid tags: {}",
+                violations.join("; ")
+            )));
+        }
+
+        Ok(normalized)
+    }
+
+    /// 単一のタグパス (リネーム先など) を正規化する
+    pub fn normalize_path(&self, tag: &str) -> Result<String> {
+        let tag = self
+            .canonicalize(tag)
+            .ok_or_else(|| AppError::BadRequest("Tag paths must not be empty".into()))?;
+        match self.violation(&tag) {
+            Some(violation) => Err(AppError::BadRequest(format!("Invalid tag: {}", violation))),
+            None => Ok(tag),
+        }
+    }
+
+    /// 既存データ用。制限違反は弾かずにそのまま残す
+    pub fn normalize_existing(&self, tags: &[String]) -> Vec<String> {
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags.iter().filter_map(|t| self.canonicalize(t)) {
+            if let Some(violation) = self.violation(&tag) {
+                tracing::warn!("Keeping tag that violates limits: {}", violation);
+            }
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer() -> TagNormalizer {
+        TagNormalizer {
+            case_fold: true,
+            max_depth: 3,
+            max_length: 10,
+        }
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn trims_segments() {
+        let n = normalizer();
+        assert_eq!(
+            n.canonicalize(" 健康 / 歯医者 ").as_deref(),
+            Some("健康/歯医者")
+        );
+    }
+
+    #[test]
+    fn drops_empty_segments() {
+        let n = normalizer();
+        assert_eq!(
+            n.canonicalize("/健康//歯医者/").as_deref(),
+            Some("健康/歯医者")
+        );
+        assert_eq!(n.canonicalize(" / / "), None);
+        assert_eq!(n.canonicalize(""), None);
+    }
+
+    #[test]
+    fn applies_nfkc() {
+        let n = normalizer();
+        // 全角英数・半角カナ・全角スラッシュ
+        assert_eq!(n.canonicalize("ＡＢＣ１").as_deref(), Some("abc1"));
+        assert_eq!(n.canonicalize("ｹﾝｺｳ").as_deref(), Some("ケンコウ"));
+        assert_eq!(n.canonicalize("a／b").as_deref(), Some("a/b"));
+    }
+
+    #[test]
+    fn case_folding_is_optional() {
+        let folded = normalizer();
+        assert_eq!(folded.canonicalize("Health").as_deref(), Some("health"));
+
+        let kept = TagNormalizer {
+            case_fold: false,
+            ..normalizer()
+        };
+        assert_eq!(kept.canonicalize("Health").as_deref(), Some("Health"));
+    }
+
+    #[test]
+    fn merges_tags_that_normalize_to_the_same_path() {
+        let n = normalizer();
+        assert_eq!(
+            n.normalize(&tags(&["A/b", " a / b", "a//b", "", "c"]))
+                .unwrap(),
+            tags(&["a/b", "c"])
+        );
+    }
+
+    #[test]
+    fn rejects_too_deep_tags() {
+        let n = normalizer();
+        assert!(n.normalize(&tags(&["a/b/c"])).is_ok());
+        assert!(matches!(
+            n.normalize(&tags(&["a/b/c/d"])),
+            Err(AppError::BadRequest(_))
+        ));
+        // 空のセグメントを詰めてから数える
+        assert!(n.normalize(&tags(&["a//b///c"])).is_ok());
+    }
+
+    #[test]
+    fn rejects_too_long_tags() {
+        let n = normalizer();
+        // 文字数で数える (バイト数ではない)
+        assert!(n.normalize(&tags(&["あいうえおかきくけこ"])).is_ok());
+        assert!(n.normalize(&tags(&["あいうえおかきくけこさ"])).is_err());
+    }
+
+    #[test]
+    fn lists_every_violation() {
+        let n = normalizer();
+        let Err(AppError::BadRequest(message)) =
+            n.normalize(&tags(&["a/b/c/d", "ok", "abcdefghijk"]))
+        else {
+            panic!("expected BadRequest");
+        };
+        assert!(message.contains("a/b/c/d: depth 4"));
+        assert!(message.contains("abcdefghijk: length 11"));
+    }
+
+    #[test]
+    fn normalize_path_rejects_empty_and_invalid_paths() {
+        let n = normalizer();
+        assert_eq!(n.normalize_path(" Ａ / b ").unwrap(), "a/b");
+        assert!(n.normalize_path(" / ").is_err());
+        assert!(n.normalize_path("a/b/c/d").is_err());
+    }
+
+    #[test]
+    fn normalize_existing_keeps_violations() {
+        let n = normalizer();
+        assert_eq!(
+            n.normalize_existing(&tags(&["a/b/c/d", "A/B/C/D", " "])),
+            tags(&["a/b/c/d"])
+        );
+    }
+}
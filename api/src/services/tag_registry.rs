@@ -0,0 +1,810 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::{
+    vectors_config, Condition, CreateCollectionBuilder, DeletePointsBuilder, Filter,
+    GetPointsBuilder, PointId, PointStruct, PointsIdsList, ScrollPointsBuilder,
+    UpsertPointsBuilder, Value, VectorParamsMap, VectorsConfig,
+};
+use serde::Serialize;
+use tokio::sync::{Mutex, OwnedRwLockReadGuard, RwLock};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::Memo;
+use crate::services::{tag_match, QdrantService, TagNormalizer};
+
+const COLLECTION_TAGS: &str = "tags";
+const COLLECTION_TAGS_DEMO: &str = "tags_demo";
+
+const SEGMENT_MATCH_WEIGHT: f64 = 0.7;
+const ROMAJI_MATCH_FACTOR: f64 = 0.8;
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// タグパスから決定的にポイントIDを作るための名前空間
+const TAG_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_9b7d_4c35_8e21_d0a9_5f3b_7c64);
+
+#[derive(Debug, Clone)]
+pub struct TagEntry {
+    pub path: String,
+    /// このタグがそのまま付いているメモの数
+    pub usage_count: u64,
+    /// このタグ以下 (子孫を含む) のいずれかが付いているメモの数
+    pub subtree_count: u64,
+    pub created_at: DateTime<Utc>,
+    /// 最後にメモへ付けられた日時
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// `GET /tags/suggest` の候補
+#[derive(Debug, Clone, Serialize)]
+pub struct TagSuggestion {
+    pub path: String,
+    pub usage_count: u64,
+    pub last_used_at: DateTime<Utc>,
+    pub score: f64,
+}
+
+/// `tags` コレクションで管理するタグマスター。メモの作成・更新・削除に合わせて件数を更新する。
+/// Qdrantにトランザクションは無いので、読み取り→書き込みはプロセス内のロックで直列化する
+#[derive(Clone)]
+pub struct TagRegistry {
+    qdrant: QdrantService,
+    normalizer: TagNormalizer,
+    lock: Arc<Mutex<()>>,
+    /// メモの書き込みから `track` までは共有、作り直しは排他で取る。
+    /// 作り直しの走査に入ったメモの差分を後から重ねて数えないようにする
+    writers: Arc<RwLock<()>>,
+}
+
+/// タグの付け替えで1件のメモがどう変わるか
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRewrite {
+    pub id: Uuid,
+    pub content: String,
+    pub tags_before: Vec<String>,
+    pub tags_after: Vec<String>,
+    /// `memos_archive` のメモか
+    pub archived: bool,
+}
+
+#[derive(Default)]
+struct CountDelta {
+    usage: i64,
+    subtree: i64,
+}
+
+impl TagRegistry {
+    pub fn new(qdrant: QdrantService, normalizer: TagNormalizer) -> Self {
+        Self {
+            qdrant,
+            normalizer,
+            lock: Arc::new(Mutex::new(())),
+            writers: Arc::new(RwLock::new(())),
+        }
+    }
+
+    /// タグの付いたメモを書き込む前に取り、`track` が終わるまで持っておく。
+    /// 持っている間は作り直し (`rebuild` / `rewrite`) が始まらない
+    pub async fn write_guard(&self) -> OwnedRwLockReadGuard<()> {
+        self.writers.clone().read_owned().await
+    }
+
+    /// メモに書き込むタグを正規化する。制限違反は `BadRequest`
+    pub fn normalize(&self, tags: &[String]) -> Result<Vec<String>> {
+        self.normalizer.normalize(tags)
+    }
+
+    /// タグコレクションを用意する。新規作成した場合はメモから組み立て直す
+    pub async fn ensure_collections(&self) -> Result<()> {
+        for demo in [false, true] {
+            let collection = tags_collection(demo);
+            let exists = self
+                .qdrant
+                .client()
+                .collection_exists(collection)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+            if exists {
+                continue;
+            }
+
+            // タグマスターはベクトルを持たない
+            self.qdrant
+                .client()
+                .create_collection(CreateCollectionBuilder::new(collection).vectors_config(
+                    VectorsConfig {
+                        config: Some(vectors_config::Config::ParamsMap(VectorParamsMap {
+                            map: HashMap::new(),
+                        })),
+                    },
+                ))
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+            tracing::info!("Created collection: {}", collection);
+
+            let count = self.rebuild(demo).await?;
+            tracing::info!("Registered {} tags in {}", count, collection);
+        }
+        Ok(())
+    }
+
+    pub async fn list(&self, demo: bool) -> Result<Vec<TagEntry>> {
+        let collection = tags_collection(demo);
+        let mut entries = Vec::new();
+        let mut offset: Option<PointId> = None;
+        let page_size = 100u32;
+
+        loop {
+            let mut scroll_builder = ScrollPointsBuilder::new(collection)
+                .with_payload(true)
+                .limit(page_size);
+
+            if let Some(ref off) = offset {
+                scroll_builder = scroll_builder.offset(off.clone());
+            }
+
+            let result = self
+                .qdrant
+                .client()
+                .scroll(scroll_builder)
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+            for point in &result.result {
+                entries.push(self.payload_to_entry(&point.payload)?);
+            }
+
+            match result.next_page_offset {
+                Some(next_offset) => offset = Some(next_offset),
+                None => break,
+            }
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// メモのタグが `old` から `new` に変わったことを反映する (作成時は `old` が空、削除時は `new` が空)
+    pub async fn record_change(&self, old: &[String], new: &[String], demo: bool) -> Result<()> {
+        let mut deltas: HashMap<String, CountDelta> = HashMap::new();
+
+        let (old_direct, old_subtree) = counted_paths(old);
+        let (new_direct, new_subtree) = counted_paths(new);
+
+        for path in new_direct.difference(&old_direct) {
+            deltas.entry(path.clone()).or_default().usage += 1;
+        }
+        for path in old_direct.difference(&new_direct) {
+            deltas.entry(path.clone()).or_default().usage -= 1;
+        }
+        for path in new_subtree.difference(&old_subtree) {
+            deltas.entry(path.clone()).or_default().subtree += 1;
+        }
+        for path in old_subtree.difference(&new_subtree) {
+            deltas.entry(path.clone()).or_default().subtree -= 1;
+        }
+
+        if deltas.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self.lock.lock().await;
+
+        let collection = tags_collection(demo);
+        let ids: Vec<PointId> = deltas.keys().map(|p| tag_point_id(p)).collect();
+        let existing = self
+            .qdrant
+            .client()
+            .get_points(GetPointsBuilder::new(collection, ids).with_payload(true))
+            .await
+            .map_err(|e| AppError::Qdrant(e.to_string()))?;
+
+        let mut current: HashMap<String, TagEntry> = HashMap::new();
+        for point in &existing.result {
+            let entry = self.payload_to_entry(&point.payload)?;
+            current.insert(entry.path.clone(), entry);
+        }
+
+        let now = Utc::now();
+        let mut upserts = Vec::new();
+        let mut removals = Vec::new();
+
+        for (path, delta) in deltas {
+            let mut entry = current.remove(&path).unwrap_or(TagEntry {
+                path: path.clone(),
+                usage_count: 0,
+                subtree_count: 0,
+                created_at: now,
+                last_used_at: now,
+            });
+            if delta.usage > 0 {
+                entry.last_used_at = now;
+            }
+            entry.usage_count = entry.usage_count.saturating_add_signed(delta.usage);
+            entry.subtree_count = entry.subtree_count.saturating_add_signed(delta.subtree);
+
+            if entry.subtree_count == 0 {
+                removals.push(tag_point_id(&path));
+            } else {
+                upserts.push(entry);
+            }
+        }
+
+        self.write(collection, upserts, removals).await
+    }
+
+    /// `record_change` の失敗はメモの書き込み自体を失敗させず、バックグラウンドで作り直して整合を取る
+    pub async fn track(&self, old: &[String], new: &[String], demo: bool) {
+        if let Err(e) = self.record_change(old, new, demo).await {
+            tracing::warn!("Failed to update tag registry ({}), rebuilding", e);
+            let registry = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = registry.rebuild(demo).await {
+                    tracing::error!("Tag registry rebuild failed: {}", e);
+                }
+            });
+        }
+    }
+
+    /// 入力途中の文字列に一致するタグを使用頻度と最終使用日時で並べて返す。
+    /// どの階層のセグメントの先頭からでも前方一致し、全角/半角・大文字/小文字・カタカナ/ひらがなを区別しない。
+    /// ローマ字入力はひらがなに変換した形でも照合する
+    pub async fn suggest(
+        &self,
+        query: &str,
+        limit: usize,
+        demo: bool,
+    ) -> Result<Vec<TagSuggestion>> {
+        Ok(rank_suggestions(
+            self.list(demo).await?,
+            query,
+            limit,
+            Utc::now(),
+        ))
+    }
+
+    /// `sources` の各タグ (子孫を含む) を `target` に付け替える。リネームは `sources` が1件の場合。
+    /// `dry_run` なら書き込まずに影響を受けるメモだけを返す
+    pub async fn rewrite(
+        &self,
+        sources: &[String],
+        target: &str,
+        dry_run: bool,
+        demo: bool,
+    ) -> Result<Vec<TagRewrite>> {
+        if sources.is_empty() {
+            return Err(AppError::BadRequest("No source tags given".into()));
+        }
+        let _writers = self.writers.write().await;
+        let sources: Vec<String> = sources
+            .iter()
+            .map(|s| self.normalizer.normalize_path(s))
+            .collect::<Result<_>>()?;
+        let sources = sources.as_slice();
+        let target = self.normalizer.normalize_path(target)?;
+        let target = target.as_str();
+        if let Some(source) = sources.iter().find(|s| is_within(target, s)) {
+            return Err(AppError::BadRequest(format!(
+                "Cannot move {} under itself ({})",
+                source, target
+            )));
+        }
+
+        let entries = self.list(demo).await?;
+        for source in sources {
+            if !entries.iter().any(|e| e.path == *source) {
+                return Err(AppError::NotFound(format!("Tag {} not found", source)));
+            }
+        }
+
+        let affected_paths: Vec<String> = entries
+            .into_iter()
+            .map(|e| e.path)
+            .filter(|path| sources.iter().any(|s| is_within(path, s)))
+            .collect();
+        let filter = Filter::must([Condition::matches("tags", affected_paths)]);
+
+        let memos = self.qdrant.scroll_memos(Some(filter), demo).await?;
+        let mut rewrites = plan_rewrites(memos, sources, target, false);
+        if !demo {
+            // アーカイブのタグはタグマスターに数えないので、アーカイブにしか無い子孫タグも拾えるよう全件から探す
+            let archived = self.qdrant.scroll_archived_memos(None).await?;
+            rewrites.extend(plan_rewrites(archived, sources, target, true));
+        }
+
+        // 付け替えで深くなる・長くなる子孫のタグが制限を超えないか、書き込む前にすべて確かめる
+        let rewritten: Vec<String> = rewrites
+            .iter()
+            .flat_map(|r| {
+                r.tags_after
+                    .iter()
+                    .filter(|tag| !r.tags_before.contains(tag))
+            })
+            .cloned()
+            .collect();
+        self.normalizer.normalize(&rewritten)?;
+
+        if dry_run || rewrites.is_empty() {
+            return Ok(rewrites);
+        }
+
+        // 書き換え後のタグが同じメモはまとめて1回の set_payload で更新する
+        let mut groups: HashMap<(bool, &[String]), Vec<Uuid>> = HashMap::new();
+        for rewrite in &rewrites {
+            groups
+                .entry((rewrite.archived, rewrite.tags_after.as_slice()))
+                .or_default()
+                .push(rewrite.id);
+        }
+        for ((archived, tags), ids) in groups {
+            if archived {
+                self.qdrant.set_archived_memo_tags(&ids, tags).await?;
+            } else {
+                self.qdrant.set_memo_tags(&ids, tags, demo).await?;
+            }
+        }
+
+        self.rebuild_locked(demo).await?;
+        tracing::info!(
+            "Rewrote tags {:?} -> {} on {} memos",
+            sources,
+            target,
+            rewrites.len()
+        );
+        Ok(rewrites)
+    }
+
+    /// メモを全件走査してタグマスターを作り直す
+    pub async fn rebuild(&self, demo: bool) -> Result<usize> {
+        let _writers = self.writers.write().await;
+        self.rebuild_locked(demo).await
+    }
+
+    /// `writers` を排他で取った状態で作り直す
+    async fn rebuild_locked(&self, demo: bool) -> Result<usize> {
+        let _guard = self.lock.lock().await;
+
+        let collection = tags_collection(demo);
+        let previous: HashMap<String, DateTime<Utc>> = self
+            .list(demo)
+            .await?
+            .into_iter()
+            .map(|e| (e.path, e.created_at))
+            .collect();
+
+        let now = Utc::now();
+        let mut entries: HashMap<String, TagEntry> = HashMap::new();
+        for memo in self.qdrant.scroll_memos(None, demo).await? {
+            let (direct, subtree) = counted_paths(&memo.tags);
+            for path in subtree {
+                let created_at = previous.get(&path).copied().unwrap_or(now);
+                let entry = entries.entry(path.clone()).or_insert(TagEntry {
+                    path: path.clone(),
+                    usage_count: 0,
+                    subtree_count: 0,
+                    created_at,
+                    last_used_at: memo.date_added,
+                });
+                entry.subtree_count += 1;
+                if direct.contains(&path) {
+                    entry.usage_count += 1;
+                }
+                entry.last_used_at = entry.last_used_at.max(memo.date_added);
+            }
+        }
+
+        let removals = previous
+            .keys()
+            .filter(|p| !entries.contains_key(*p))
+            .map(|p| tag_point_id(p))
+            .collect();
+        let count = entries.len();
+
+        self.write(collection, entries.into_values().collect(), removals)
+            .await?;
+        Ok(count)
+    }
+
+    async fn write(
+        &self,
+        collection: &str,
+        upserts: Vec<TagEntry>,
+        removals: Vec<PointId>,
+    ) -> Result<()> {
+        if !upserts.is_empty() {
+            let points: Vec<PointStruct> = upserts
+                .iter()
+                .map(|entry| {
+                    PointStruct::new(
+                        tag_point_id(&entry.path),
+                        HashMap::<String, Vec<f32>>::new(),
+                        entry_to_payload(entry),
+                    )
+                })
+                .collect();
+
+            self.qdrant
+                .client()
+                .upsert_points(UpsertPointsBuilder::new(collection, points).wait(true))
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        }
+
+        if !removals.is_empty() {
+            self.qdrant
+                .client()
+                .delete_points(
+                    DeletePointsBuilder::new(collection)
+                        .points(PointsIdsList { ids: removals })
+                        .wait(true),
+                )
+                .await
+                .map_err(|e| AppError::Qdrant(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    fn payload_to_entry(&self, payload: &HashMap<String, Value>) -> Result<TagEntry> {
+        let created_at = self.qdrant.get_datetime_field(payload, "created_at")?;
+        Ok(TagEntry {
+            path: self.qdrant.get_string_field(payload, "path")?,
+            usage_count: self.qdrant.get_int_field(payload, "usage_count")? as u64,
+            subtree_count: self.qdrant.get_int_field(payload, "subtree_count")? as u64,
+            created_at,
+            last_used_at: self
+                .qdrant
+                .get_datetime_field(payload, "last_used_at")
+                .unwrap_or(created_at),
+        })
+    }
+}
+
+fn tags_collection(demo: bool) -> &'static str {
+    if demo {
+        COLLECTION_TAGS_DEMO
+    } else {
+        COLLECTION_TAGS
+    }
+}
+
+fn tag_point_id(path: &str) -> PointId {
+    Uuid::new_v5(&TAG_NAMESPACE, path.as_bytes())
+        .to_string()
+        .into()
+}
+
+fn entry_to_payload(entry: &TagEntry) -> HashMap<String, Value> {
+    let mut payload: HashMap<String, Value> = HashMap::new();
+    payload.insert("path".into(), entry.path.clone().into());
+    payload.insert("usage_count".into(), (entry.usage_count as i64).into());
+    payload.insert("subtree_count".into(), (entry.subtree_count as i64).into());
+    payload.insert("created_at".into(), entry.created_at.to_rfc3339().into());
+    payload.insert(
+        "last_used_at".into(),
+        entry.last_used_at.to_rfc3339().into(),
+    );
+    payload
+}
+
+/// `suggest` の並べ替え。一致度 × 使用回数 (対数) × 最近使ったか
+fn rank_suggestions(
+    entries: Vec<TagEntry>,
+    query: &str,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> Vec<TagSuggestion> {
+    let key = tag_match::match_key(query.trim());
+    let romaji_key = tag_match::romaji_to_hiragana(&key);
+
+    let mut suggestions: Vec<TagSuggestion> = entries
+        .into_iter()
+        .filter(|entry| entry.usage_count > 0)
+        .filter_map(|entry| {
+            let path_key = tag_match::match_key(&entry.path);
+            let weight = match_weight(&path_key, &key).or_else(|| {
+                romaji_key
+                    .as_deref()
+                    .and_then(|k| match_weight(&path_key, k))
+                    .map(|w| w * ROMAJI_MATCH_FACTOR)
+            })?;
+
+            let days = (now - entry.last_used_at).num_days().max(0) as f64;
+            let recency = 1.0 / (1.0 + days / RECENCY_HALF_LIFE_DAYS);
+            let score = weight * (1.0 + (entry.usage_count as f64).ln_1p()) * (0.5 + 0.5 * recency);
+
+            Some(TagSuggestion {
+                path: entry.path,
+                usage_count: entry.usage_count,
+                last_used_at: entry.last_used_at,
+                score,
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// 照合キー同士の一致度。パス全体の前方一致 > 下位セグメントの前方一致。一致しなければ None
+fn match_weight(path_key: &str, query_key: &str) -> Option<f64> {
+    if path_key.starts_with(query_key) {
+        return Some(1.0);
+    }
+    path_key
+        .match_indices('/')
+        .any(|(i, _)| path_key[i + 1..].starts_with(query_key))
+        .then_some(SEGMENT_MATCH_WEIGHT)
+}
+
+/// `path` が `ancestor` 自身かその子孫か
+fn is_within(path: &str, ancestor: &str) -> bool {
+    path == ancestor
+        || path
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// タグが変わるメモだけを、書き換え後のタグ (重複はまとめる) と組にして返す
+fn plan_rewrites(
+    memos: Vec<Memo>,
+    sources: &[String],
+    target: &str,
+    archived: bool,
+) -> Vec<TagRewrite> {
+    let mut rewrites = Vec::new();
+    for memo in memos {
+        let mut tags_after: Vec<String> = Vec::new();
+        for tag in &memo.tags {
+            let tag = rewrite_path(tag, sources, target);
+            if !tags_after.contains(&tag) {
+                tags_after.push(tag);
+            }
+        }
+        if tags_after != memo.tags {
+            rewrites.push(TagRewrite {
+                id: memo.id,
+                content: memo.content,
+                tags_before: memo.tags,
+                tags_after,
+                archived,
+            });
+        }
+    }
+    rewrites
+}
+
+fn rewrite_path(tag: &str, sources: &[String], target: &str) -> String {
+    for source in sources {
+        if tag == source {
+            return target.to_string();
+        }
+        if let Some(rest) = tag.strip_prefix(source.as_str()) {
+            if rest.starts_with('/') {
+                return format!("{}{}", target, rest);
+            }
+        }
+    }
+    tag.to_string()
+}
+
+/// メモのタグから (直接付いているパス, 祖先を含む全パス) を求める
+fn counted_paths(tags: &[String]) -> (HashSet<String>, HashSet<String>) {
+    let direct: HashSet<String> = tags.iter().cloned().collect();
+    let mut subtree = HashSet::new();
+
+    for tag in &direct {
+        let parts: Vec<&str> = tag.split('/').collect();
+        for depth in 1..=parts.len() {
+            subtree.insert(parts[..depth].join("/"));
+        }
+    }
+
+    (direct, subtree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn set(paths: &[&str]) -> HashSet<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn counted_paths_include_ancestors() {
+        let (direct, subtree) = counted_paths(&tags(&["仕事/会議/定例", "趣味"]));
+        assert_eq!(direct, set(&["仕事/会議/定例", "趣味"]));
+        assert_eq!(
+            subtree,
+            set(&["仕事", "仕事/会議", "仕事/会議/定例", "趣味"])
+        );
+    }
+
+    #[test]
+    fn counted_paths_count_shared_ancestors_once() {
+        // 同じ祖先を持つタグが2つあっても、メモ1件として数える
+        let (direct, subtree) = counted_paths(&tags(&["仕事/会議", "仕事/メール", "仕事"]));
+        assert_eq!(direct, set(&["仕事/会議", "仕事/メール", "仕事"]));
+        assert_eq!(subtree, set(&["仕事", "仕事/会議", "仕事/メール"]));
+    }
+
+    #[test]
+    fn counted_paths_of_no_tags() {
+        let (direct, subtree) = counted_paths(&[]);
+        assert!(direct.is_empty());
+        assert!(subtree.is_empty());
+    }
+
+    fn memo(paths: &[&str]) -> Memo {
+        Memo {
+            id: Uuid::new_v4(),
+            content: paths.join(" "),
+            memo_type: crate::models::MemoType::Flash,
+            from: None,
+            until: None,
+            timezone: None,
+            tags: tags(paths),
+            date_added: Utc::now(),
+            access_count: 0,
+            last_accessed: Utc::now(),
+            completed: false,
+            embedding_status: Default::default(),
+            ics_uid: None,
+            recurrence: None,
+            completed_occurrences: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn is_within_respects_segment_boundaries() {
+        assert!(is_within("a/b", "a/b"));
+        assert!(is_within("a/b/c", "a/b"));
+        assert!(!is_within("a/bc", "a/b"));
+        assert!(!is_within("a", "a/b"));
+    }
+
+    #[test]
+    fn moving_under_own_descendant_is_detected() {
+        // rewrite はこの判定で「自分の下への移動」を弾く
+        assert!(is_within("a/b/c", "a"));
+        assert!(is_within("a", "a"));
+        assert!(!is_within("ab/c", "a"));
+    }
+
+    #[test]
+    fn rewrite_path_moves_descendants_but_not_prefix_siblings() {
+        let sources = tags(&["a/b"]);
+        assert_eq!(rewrite_path("a/b", &sources, "x"), "x");
+        assert_eq!(rewrite_path("a/b/c", &sources, "x"), "x/c");
+        assert_eq!(rewrite_path("a/bc", &sources, "x"), "a/bc");
+        assert_eq!(rewrite_path("a", &sources, "x"), "a");
+    }
+
+    #[test]
+    fn rewrite_path_merges_several_sources() {
+        let sources = tags(&["a", "b"]);
+        assert_eq!(rewrite_path("a/c", &sources, "t"), "t/c");
+        assert_eq!(rewrite_path("b/c", &sources, "t"), "t/c");
+        assert_eq!(rewrite_path("c", &sources, "t"), "c");
+    }
+
+    #[test]
+    fn plan_rewrites_skips_unchanged_memos() {
+        let rewrites = plan_rewrites(
+            vec![memo(&["a/bc"]), memo(&["a/b/c", "z"])],
+            &tags(&["a/b"]),
+            "x",
+            false,
+        );
+        assert_eq!(rewrites.len(), 1);
+        assert_eq!(rewrites[0].tags_before, tags(&["a/b/c", "z"]));
+        assert_eq!(rewrites[0].tags_after, tags(&["x/c", "z"]));
+        assert!(!rewrites[0].archived);
+    }
+
+    #[test]
+    fn plan_rewrites_merges_into_existing_tag() {
+        // 統合先のタグが既に付いているメモは、重複させずに1つにまとめる
+        let rewrites = plan_rewrites(
+            vec![memo(&["old", "new", "old/sub", "new/sub"])],
+            &tags(&["old"]),
+            "new",
+            true,
+        );
+        assert_eq!(rewrites.len(), 1);
+        assert_eq!(rewrites[0].tags_after, tags(&["new", "new/sub"]));
+        assert!(rewrites[0].archived);
+    }
+
+    fn entry(path: &str, usage_count: u64, days_ago: i64, now: DateTime<Utc>) -> TagEntry {
+        TagEntry {
+            path: path.to_string(),
+            usage_count,
+            subtree_count: usage_count,
+            created_at: now,
+            last_used_at: now - chrono::Duration::days(days_ago),
+        }
+    }
+
+    fn ranked(entries: Vec<TagEntry>, query: &str, now: DateTime<Utc>) -> Vec<String> {
+        rank_suggestions(entries, query, 10, now)
+            .into_iter()
+            .map(|s| s.path)
+            .collect()
+    }
+
+    #[test]
+    fn match_weight_prefers_whole_path_prefix() {
+        assert_eq!(match_weight("健康/歯医者", "健康"), Some(1.0));
+        assert_eq!(
+            match_weight("健康/歯医者", "歯医"),
+            Some(SEGMENT_MATCH_WEIGHT)
+        );
+        // セグメントの途中からは一致しない
+        assert_eq!(match_weight("健康/歯医者", "医者"), None);
+    }
+
+    #[test]
+    fn suggest_ranks_prefix_matches_above_segment_matches() {
+        let now = Utc::now();
+        let entries = vec![
+            entry("仕事/会議", 5, 0, now),
+            entry("会議室", 5, 0, now),
+            entry("趣味", 5, 0, now),
+        ];
+        assert_eq!(ranked(entries, "会議", now), vec!["会議室", "仕事/会議"]);
+    }
+
+    #[test]
+    fn suggest_weighs_usage_and_recency() {
+        let now = Utc::now();
+        let entries = vec![
+            entry("a-rare", 1, 0, now),
+            entry("a-often", 50, 0, now),
+            entry("a-old", 50, 365, now),
+        ];
+        assert_eq!(
+            ranked(entries, "a", now),
+            vec!["a-often", "a-old", "a-rare"]
+        );
+    }
+
+    #[test]
+    fn suggest_matches_kana_and_romaji() {
+        let now = Utc::now();
+        let entries = vec![entry("ケンコウ", 3, 0, now), entry("けんさ", 3, 0, now)];
+        // カタカナ・ひらがなを区別しない
+        assert_eq!(ranked(entries.clone(), "けんこ", now), vec!["ケンコウ"]);
+        // ローマ字はひらがなに直して照合し、そのぶん低く見る
+        let suggestions = rank_suggestions(entries, "kenko", 10, now);
+        assert_eq!(suggestions.len(), 1);
+        let direct = rank_suggestions(vec![entry("kenko", 3, 0, now)], "kenko", 10, now);
+        assert!(suggestions[0].score < direct[0].score);
+    }
+
+    #[test]
+    fn suggest_skips_unused_tags_and_respects_limit() {
+        let now = Utc::now();
+        let entries = vec![
+            entry("a", 0, 0, now),
+            entry("ab", 1, 0, now),
+            entry("ac", 1, 0, now),
+        ];
+        let suggestions = rank_suggestions(entries, "a", 1, now);
+        // 同点はパス順
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].path, "ab");
+    }
+}
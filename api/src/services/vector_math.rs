@@ -0,0 +1,13 @@
+/// 内積。正規化済みのベクトル同士ならコサイン類似度になる
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// 長さ1に揃える。ゼロベクトルはそのまま返す
+pub(crate) fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = dot(&vector, &vector).sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
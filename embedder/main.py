@@ -1,3 +1,5 @@
+import os
+
 import torch
 from fastapi import FastAPI, HTTPException
 from pydantic import BaseModel
@@ -14,10 +16,13 @@ device = "cuda" if torch.cuda.is_available() else "cpu"
 print(f"Loading model on: {device}")
 
 # モデルのロード (起動時に1回だけ行う)
-# multilingual-e5-base: 278M params, 768次元
-print("Loading Embedding Model: multilingual-e5-base...")
-embed_model = SentenceTransformer("intfloat/multilingual-e5-base", device=device)
-print("Model loaded successfully!")
+# デフォルトは multilingual-e5-base: 278M params, 768次元
+# モデルを変更した場合は API 側で再インデックスが必要 (POST /admin/reindex)
+MODEL_NAME = os.environ.get("EMBEDDING_MODEL", "intfloat/multilingual-e5-base")
+print(f"Loading Embedding Model: {MODEL_NAME}...")
+embed_model = SentenceTransformer(MODEL_NAME, device=device)
+MODEL_DIMENSION = embed_model.get_sentence_embedding_dimension()
+print(f"Model loaded successfully! (dimension: {MODEL_DIMENSION})")
 
 
 # --- リクエスト/レスポンスの型定義 ---
@@ -42,6 +47,7 @@ class HealthResponse(BaseModel):
     status: str
     device: str
     model: str
+    dimension: int
 
 
 # --- エンドポイント ---
@@ -51,14 +57,14 @@ class HealthResponse(BaseModel):
 def health_check() -> HealthResponse:
     """ヘルスチェック"""
     return HealthResponse(
-        status="ok", device=device, model="intfloat/multilingual-e5-base"
+        status="ok", device=device, model=MODEL_NAME, dimension=MODEL_DIMENSION
     )
 
 
 @app.post("/embed", response_model=EmbedResponse)
 def create_embedding(req: EmbedRequest) -> EmbedResponse:
     """
-    テキストを受け取り、768次元のベクトルを返す
+    テキストを受け取り、モデルの次元数 (e5-base は768次元) のベクトルを返す
 
     注意: multilingual-e5 モデルはプレフィックスが必要
     - 保存時: "passage: {content}"
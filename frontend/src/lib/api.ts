@@ -2,9 +2,17 @@ import type {
 	Memo,
 	CreateMemoRequest,
 	UpdateMemoRequest,
+	CompletionResponse,
 	SearchRequest,
 	SearchResponse,
 	TagsResponse,
+	TagSuggestResponse,
+	RecommendTagsResponse,
+	AgendaResponse,
+	ImportResponse,
+	Reminder,
+	ReminderNotification,
+	ReminderStatus,
 	ApiError
 } from './types';
 
@@ -62,12 +70,30 @@ class ApiClient {
 		});
 	}
 
+	async completeMemo(id: string, occurrence?: string): Promise<CompletionResponse> {
+		const query = occurrence ? `?occurrence=${occurrence}` : '';
+		return this.request<CompletionResponse>(`/memo/${id}/complete${query}`, {
+			method: 'POST'
+		});
+	}
+
+	async uncompleteMemo(id: string, occurrence?: string): Promise<CompletionResponse> {
+		const query = occurrence ? `?occurrence=${occurrence}` : '';
+		return this.request<CompletionResponse>(`/memo/${id}/uncomplete${query}`, {
+			method: 'POST'
+		});
+	}
+
 	async deleteMemo(id: string): Promise<void> {
 		await this.request<void>(`/memo/${id}`, {
 			method: 'DELETE'
 		});
 	}
 
+	async getRelatedMemos(id: string, limit = 10): Promise<SearchResponse> {
+		return this.request<SearchResponse>(`/memo/${id}/related?limit=${limit}`);
+	}
+
 	// Search operations
 	async search(data: SearchRequest): Promise<SearchResponse> {
 		return this.request<SearchResponse>('/search', {
@@ -84,10 +110,68 @@ class ApiClient {
 	}
 
 	// Tag operations
+	async getAgenda(from?: string, to?: string): Promise<AgendaResponse> {
+		const params = new URLSearchParams({
+			timezone: Intl.DateTimeFormat().resolvedOptions().timeZone
+		});
+		if (from) params.set('from', from);
+		if (to) params.set('to', to);
+		return this.request<AgendaResponse>(`/agenda?${params}`);
+	}
+
+	async importIcs(ics: string): Promise<ImportResponse> {
+		return this.request<ImportResponse>('/import/ics', {
+			method: 'POST',
+			headers: { 'Content-Type': 'text/calendar' },
+			body: ics
+		});
+	}
+
+	async createReminder(memoId: string, remindAt: string): Promise<Reminder> {
+		return this.request<Reminder>(`/memo/${memoId}/reminders`, {
+			method: 'POST',
+			body: JSON.stringify({ remind_at: remindAt })
+		});
+	}
+
+	async listReminders(filter: { memoId?: string; status?: ReminderStatus } = {}): Promise<Reminder[]> {
+		const params = new URLSearchParams();
+		if (filter.memoId) params.set('memo_id', filter.memoId);
+		if (filter.status) params.set('status', filter.status);
+		return this.request<Reminder[]>(`/reminders?${params}`);
+	}
+
+	async deleteReminder(id: string): Promise<void> {
+		await this.request<void>(`/reminders/${id}`, {
+			method: 'DELETE'
+		});
+	}
+
+	// Returns a function that closes the stream
+	subscribeReminders(onReminder: (notification: ReminderNotification) => void): () => void {
+		const source = new EventSource(`${API_BASE}/reminders/stream`);
+		source.addEventListener('reminder', (event) => {
+			onReminder(JSON.parse((event as MessageEvent).data));
+		});
+		return () => source.close();
+	}
+
 	async getTags(): Promise<TagsResponse> {
 		return this.request<TagsResponse>('/tags');
 	}
 
+	async suggestTags(q: string, limit = 10): Promise<TagSuggestResponse> {
+		const params = new URLSearchParams({ q, limit: String(limit) });
+		return this.request<TagSuggestResponse>(`/tags/suggest?${params}`);
+	}
+
+	async recommendTags(content: string, limit = 5): Promise<RecommendTagsResponse> {
+		return this.request<RecommendTagsResponse>('/tags/recommend', {
+			method: 'POST',
+			body: JSON.stringify({ content, limit })
+		});
+	}
+
 	// Health check
 	async health(): Promise<{ status: string; version: string }> {
 		return this.request<{ status: string; version: string }>('/health');
@@ -5,13 +5,21 @@ export interface Memo {
 	id: string;
 	content: string;
 	type: MemoType;
-	from?: string; // ISO date string (YYYY-MM-DD)
-	until?: string; // ISO date string (YYYY-MM-DD)
+	from?: string; // YYYY-MM-DD (all day) or YYYY-MM-DDTHH:MM
+	until?: string; // same format as from
+	timezone?: string; // IANA name for the times (defaults to the server's DEFAULT_TIMEZONE)
 	tags: string[];
 	date_added: string; // ISO datetime string
 	access_count: number;
 	last_accessed: string; // ISO datetime string
 	completed: boolean;
+	embedding_status: 'ready' | 'pending';
+	recurrence: string | null; // RRULE subset, e.g. FREQ=WEEKLY;BYDAY=TU
+	completed_occurrences: string[]; // first days of completed occurrences
+}
+
+export interface CompletionResponse extends Memo {
+	archived: boolean; // moved to the archive because ARCHIVE_ON_COMPLETE is enabled
 }
 
 export interface CreateMemoRequest {
@@ -19,7 +27,35 @@ export interface CreateMemoRequest {
 	type: MemoType;
 	from?: string;
 	until?: string;
+	timezone?: string;
 	tags?: string[];
+	recurrence?: string; // requires from (the first occurrence)
+	force?: boolean; // create even when near-duplicates exist
+}
+
+export interface DuplicateCandidate extends Memo {
+	similarity: number;
+}
+
+export interface DuplicatesFoundResponse {
+	error: string;
+	duplicates: DuplicateCandidate[];
+}
+
+export interface DuplicateCluster {
+	memos: Memo[];
+	max_similarity: number;
+}
+
+export interface DuplicatesResponse {
+	clusters: DuplicateCluster[];
+	total: number;
+}
+
+export interface MergeMemosRequest {
+	ids: string[];
+	separator?: string;
+	archive_sources?: boolean; // move sources to the archive instead of deleting them
 }
 
 export interface UpdateMemoRequest {
@@ -27,8 +63,10 @@ export interface UpdateMemoRequest {
 	type?: MemoType;
 	from?: string;
 	until?: string;
+	timezone?: string | null; // null falls back to the server's DEFAULT_TIMEZONE
 	tags?: string[];
 	completed?: boolean;
+	recurrence?: string | null; // null removes the recurrence
 }
 
 // Search types
@@ -37,12 +75,20 @@ export interface SearchFilters {
 	until_lte?: string; // until <= this date
 	tags?: string[];
 	type?: MemoType;
+	completed?: boolean;
+	phrases?: string[]; // content must contain each phrase verbatim
+	preset?: DatePreset; // explicit from_gte / until_lte take precedence
+	timezone?: string; // IANA name, e.g. Asia/Tokyo (defaults to the server's DEFAULT_TIMEZONE)
+	date_match?: DateMatch; // defaults to 'overlaps'
 }
 
+export type DateMatch = 'overlaps' | 'contained' | 'starts_within' | 'single_day';
+
 export interface SearchRequest {
 	query: string;
 	filters?: SearchFilters;
 	limit?: number;
+	diversity?: number; // 0-1, higher values push near-identical results apart
 }
 
 export interface SearchResult {
@@ -60,12 +106,27 @@ export interface SearchResult {
 export interface SearchResponse {
 	results: SearchResult[];
 	total: number;
+	degraded: boolean; // true when the embedder was down and lexical matching was used
+	interpretation?: QueryInterpretation;
+}
+
+// Operators parsed out of the query string, rendered as chips
+export interface QueryInterpretation {
+	text: string; // free text left after removing operators (the part that gets embedded)
+	phrases?: string[];
+	tags?: string[];
+	type?: MemoType;
+	from_gte?: string;
+	until_lte?: string;
+	completed?: boolean;
 }
 
 // Tag types
 export interface TagNode {
 	name: string;
 	path: string;
+	memo_count: number; // memos tagged with exactly this path
+	subtree_memo_count: number; // distinct memos tagged with this path or a descendant
 	children: TagNode[];
 }
 
@@ -73,6 +134,119 @@ export interface TagsResponse {
 	tags: TagNode[];
 }
 
+export interface TagSuggestion {
+	path: string;
+	usage_count: number;
+	last_used_at: string;
+	score: number;
+}
+
+export interface TagSuggestResponse {
+	suggestions: TagSuggestion[];
+}
+
+export interface TagRecommendation {
+	path: string;
+	confidence: number; // 0-1
+	memo_count: number;
+}
+
+export interface RecommendTagsResponse {
+	recommendations: TagRecommendation[];
+}
+
+export interface TagRewrite {
+	id: string;
+	content: string;
+	tags_before: string[];
+	tags_after: string[];
+	archived: boolean; // the memo lives in memos_archive
+}
+
+export interface TagRewriteResponse {
+	dry_run: boolean;
+	total: number;
+	memos: TagRewrite[];
+}
+
+// Cluster types
+export interface ClusterMemo extends Memo {
+	similarity: number;
+}
+
+export interface ClusterTag {
+	path: string;
+	share: number;
+}
+
+export interface MemoCluster {
+	size: number;
+	representatives: ClusterMemo[];
+	dominant_tags: ClusterTag[];
+	suggested_tag: string | null;
+}
+
+export interface ClusterReport {
+	clusters: MemoCluster[];
+	memo_count: number;
+	computed_at: string;
+}
+
+// Agenda types
+export interface AgendaMemo extends Memo {
+	occurrence?: string; // recurring memos: first day of this occurrence
+}
+
+export interface AgendaDay {
+	date: string; // YYYY-MM-DD
+	memos: AgendaMemo[]; // multi-day memos appear on every day they cover
+}
+
+export interface AgendaResponse {
+	from: string;
+	to: string;
+	overdue: Memo[]; // incomplete flash memos whose last day has passed
+	days: AgendaDay[]; // only days that have memos
+}
+
+// iCalendar import types
+export interface SkippedEvent {
+	uid: string | null;
+	reason: string;
+}
+
+export interface ImportResponse {
+	created: number;
+	updated: number;
+	unchanged: number; // already imported with identical content
+	skipped: SkippedEvent[];
+}
+
+// Reminder types
+export type ReminderStatus = 'pending' | 'sent' | 'failed' | 'cancelled';
+
+export interface Reminder {
+	id: string;
+	memo_id: string;
+	remind_at: string; // RFC 3339
+	status: ReminderStatus;
+	created_at: string;
+	sent_at: string | null;
+	attempts: number; // failed delivery attempts
+	last_error: string | null;
+}
+
+export interface ReminderNotification {
+	reminder_id: string;
+	memo_id: string;
+	remind_at: string;
+	content: string;
+	tags: string[];
+	from: string | null;
+	until: string | null;
+	timezone: string | null;
+}
+
 // Auth state
 export interface AuthState {
 	isAuthenticated: boolean;
@@ -85,46 +259,5 @@ export interface ApiError {
 	status: number;
 }
 
-// Date filter presets
+// Date filter presets (resolved on the server in SearchFilters.timezone)
 export type DatePreset = 'today' | 'tomorrow' | 'this_week' | 'next_week' | 'this_month';
-
-export function getDateRangeForPreset(preset: DatePreset): { from: string; until: string } {
-	const today = new Date();
-	const yyyy = (d: Date) => d.toISOString().split('T')[0];
-
-	switch (preset) {
-		case 'today': {
-			const date = yyyy(today);
-			return { from: date, until: date };
-		}
-		case 'tomorrow': {
-			const tomorrow = new Date(today);
-			tomorrow.setDate(tomorrow.getDate() + 1);
-			const date = yyyy(tomorrow);
-			return { from: date, until: date };
-		}
-		case 'this_week': {
-			const startOfWeek = new Date(today);
-			const day = today.getDay();
-			const diff = day === 0 ? -6 : 1 - day; // Monday as start of week
-			startOfWeek.setDate(today.getDate() + diff);
-			const endOfWeek = new Date(startOfWeek);
-			endOfWeek.setDate(startOfWeek.getDate() + 6);
-			return { from: yyyy(startOfWeek), until: yyyy(endOfWeek) };
-		}
-		case 'next_week': {
-			const startOfNextWeek = new Date(today);
-			const day = today.getDay();
-			const diff = day === 0 ? 1 : 8 - day;
-			startOfNextWeek.setDate(today.getDate() + diff);
-			const endOfNextWeek = new Date(startOfNextWeek);
-			endOfNextWeek.setDate(startOfNextWeek.getDate() + 6);
-			return { from: yyyy(startOfNextWeek), until: yyyy(endOfNextWeek) };
-		}
-		case 'this_month': {
-			const startOfMonth = new Date(today.getFullYear(), today.getMonth(), 1);
-			const endOfMonth = new Date(today.getFullYear(), today.getMonth() + 1, 0);
-			return { from: yyyy(startOfMonth), until: yyyy(endOfMonth) };
-		}
-	}
-}